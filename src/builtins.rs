@@ -0,0 +1,125 @@
+use crate::element::Element;
+use crate::parser::Span;
+use crate::process;
+use crate::processor::{ArgumentType, BinaryOperator, CustomBinaryOperator, CustomUnaryOperator, ElementProcessor, Function, Parameter, ProcessResult, ProcessWarning};
+use crate::{object_element, string_element};
+
+// A processor with the built-in functions and operators, as used by the command line tool
+pub fn create_processor() -> ElementProcessor<'static> {
+    with_builtins(ElementProcessor::new())
+}
+
+// Adds the constant folding and the built-ins to a processor. Functions that the processor
+// already has, like the ones from registry files, take precedence over the built-in ones.
+pub fn with_builtins(mut processor: ElementProcessor<'_>) -> ElementProcessor<'_> {
+    processor.add_postprocessor(process::fold_constants);
+    add_builtins(&mut processor);
+    processor
+}
+
+// Functions that are added before these take precedence over them
+pub fn add_builtins(processor: &mut ElementProcessor<'_>) {
+    processor.add_function(Function::new(String::from("blend_alpha"), Vec::new()));
+    processor.add_function(Function::new(String::from("blend_offset"), Vec::new()));
+    processor.add_function(Function::new(String::from("beardifier"), Vec::new()));
+    processor.add_function(Function::new(String::from("old_blended_noise"), Vec::new()));
+    processor.add_function(Function::new(String::from("end_islands"), Vec::new()));
+
+    processor.add_function(Function::new_with_method_syntax(String::from("abs"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("half_negative"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("quarter_negative"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("square"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("cube"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("squeeze"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("interpolated"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("flat_cache"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("cache_2d"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("cache_once"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("cache_all_in_cell"), vec![Parameter::density_function("argument")]));
+    processor.add_function(Function::new_with_method_syntax(String::from("slide"), vec![Parameter::density_function("argument")]));
+
+    processor.add_function(Function::new(String::from("min"),
+        vec![Parameter::density_function("argument1"), Parameter::density_function("argument2")]));
+    processor.add_function(Function::new(String::from("max"),
+        vec![Parameter::density_function("argument1"), Parameter::density_function("argument2")]));
+
+    processor.add_function(Function::new_with_method_syntax(String::from("clamp"), vec![
+        Parameter::density_function("input"), Parameter::new("min", ArgumentType::Number), Parameter::new("max", ArgumentType::Number)
+    ]));
+    processor.add_function(Function::new_with_method_syntax(String::from("range_choice"), vec![
+        Parameter::density_function("input"),
+        Parameter::new("min_inclusive", ArgumentType::Number), Parameter::new("max_exclusive", ArgumentType::Number),
+        Parameter::density_function("when_in_range"), Parameter::density_function("when_out_of_range")
+    ]));
+    processor.add_function(Function::new(String::from("noise"), vec![
+        Parameter::new("noise", ArgumentType::NoiseId),
+        Parameter::optional("xz_scale", ArgumentType::Number, Element::FloatElement(1.0, Span::none())),
+        Parameter::optional("y_scale", ArgumentType::Number, Element::FloatElement(1.0, Span::none()))
+    ]));
+    processor.add_function(Function::new(String::from("shifted_noise"), vec![
+        Parameter::new("noise", ArgumentType::NoiseId),
+        Parameter::density_function("shift_x"), Parameter::density_function("shift_y"), Parameter::density_function("shift_z"),
+        Parameter::optional("xz_scale", ArgumentType::Number, Element::FloatElement(1.0, Span::none())),
+        Parameter::optional("y_scale", ArgumentType::Number, Element::FloatElement(1.0, Span::none()))
+    ]));
+    processor.add_function(Function::new_with_method_syntax(String::from("weird_scaled_sampler"), vec![
+        Parameter::density_function("input"),
+        Parameter::new("noise", ArgumentType::NoiseId),
+        Parameter::new("rarity_value_mapper", ArgumentType::OneOf(&["type_1", "type_2"]))
+    ]));
+
+    processor.add_function(Function::new_custom(String::from("y_clamped_gradient"), vec![
+        Parameter::new("from_y", ArgumentType::Int), Parameter::new("to_y", ArgumentType::Int),
+        Parameter::new("from_value", ArgumentType::Number), Parameter::new("to_value", ArgumentType::Number)
+    ], false, Box::new(|arguments| {
+        let [from_y, to_y, from_value, to_value]: [Element; 4] = arguments.try_into().expect("y_clamped_gradient has four parameters");
+        process::y_clamped_gradient(from_y, to_y, from_value, to_value)
+    })));
+    processor.add_function(Function::new_custom(String::from("y_gradient"), vec![
+        Parameter::new("from", ArgumentType::Any), Parameter::new("to", ArgumentType::Any)
+    ], false, Box::new(|arguments| {
+        let [from, to]: [Element; 2] = arguments.try_into().expect("y_gradient has two parameters");
+        process::y_gradient(from, to)
+    })));
+
+    processor.add_function(Function::new_custom(String::from("lerp"), vec![
+        Parameter::density_function("delta"), Parameter::density_function("a"), Parameter::density_function("b")
+    ], false, Box::new(|arguments| {
+        let [a, b, c]: [Element; 3] = arguments.try_into().expect("lerp has three parameters");
+
+        ProcessResult::new(object_element!(
+            string_element!("type") => string_element!("minecraft:add"),
+            string_element!("argument1") => object_element!(
+                string_element!("type") => string_element!("minecraft:mul"),
+                string_element!("argument1") => b,
+                string_element!("argument2") => object_element!(
+                    string_element!("type") => string_element!("minecraft:add"),
+                    string_element!("argument1") => Element::FloatElement(1.0, Span::none()),
+                    string_element!("argument2") => object_element!(
+                        string_element!("type") => string_element!("minecraft:mul"),
+                        string_element!("argument1") => Element::FloatElement(-1.0, Span::none()), // In the same order as vanilla
+                        string_element!("argument2") => object_element!(
+                            string_element!("type") => string_element!("minecraft:cache_once"),
+                            string_element!("argument") => a.clone()
+                        )
+                    )
+                )
+            ),
+            string_element!("argument2") => object_element!(
+                string_element!("type") => string_element!("minecraft:mul"),
+                string_element!("argument1") => c,
+                string_element!("argument2") => object_element!(
+                    string_element!("type") => string_element!("minecraft:cache_once"),
+                    string_element!("argument") => a.clone()
+                )
+            )
+    ), vec![ ProcessWarning::LerpDuplicatedCode(Element::FunctionCallElement {
+            span: a.span(), receiver: Some(Box::new(a)), name: String::from("cache_once"), arguments: Some(Vec::new()) }) ], Vec::new())
+    })));
+
+    processor.add_binary_operator(BinaryOperator::new(String::from("+"), String::from("add")));
+    processor.add_binary_operator(BinaryOperator::new(String::from("*"), String::from("mul")));
+    processor.add_custom_binary_operator(CustomBinaryOperator::new(String::from("-"), Box::new(process::subtract)));
+    processor.add_custom_binary_operator(CustomBinaryOperator::new(String::from("/"), Box::new(process::divide)));
+    processor.add_custom_unary_operator(CustomUnaryOperator::new(String::from("-"), Box::new(process::negate)));
+}
@@ -0,0 +1,120 @@
+use std::path::Path;
+
+use crate::diagnostic::Diagnostic;
+use crate::format;
+use crate::import::Importer;
+use crate::io::FileResult;
+use crate::module::Declaration;
+use crate::parser::{LangParser, SourceFiles};
+use crate::parser::lexer::LangLexer;
+use crate::processor::{ElementProcessor, Function, Parameter, ProcessError};
+use crate::util;
+
+// The result of compiling one source file. The files contain the source file and its imports,
+// which are needed to render the diagnostics.
+pub struct Compilation {
+    pub result: FileResult,
+    pub diagnostics: Vec<Diagnostic>,
+    pub files: SourceFiles,
+}
+
+// Turns a source file into density function JSON, reporting all errors that can be found
+pub fn compile_file(path: &Path, source: String, importer: &Importer, processor: &ElementProcessor<'_>,
+                    options: format::Options) -> Compilation {
+    let mut files = SourceFiles::new();
+    let file = files.add(path.to_path_buf(), source);
+    let mut diagnostics = Vec::new();
+
+    let lexer = LangLexer::new(files.source(file).expect("file was just added")); // Moved into parser
+    let mut parser = LangParser::new_with_file(lexer, file);
+
+    // Files with syntax errors are still processed, to report the other errors as well
+    let (module, parse_errors) = parser.parse_module_with_recovery();
+    diagnostics.extend(parse_errors.iter().map(|err| Diagnostic::from_parse_error(err, file)));
+
+    let imports = match importer.load_imports(path, &module, &mut files) {
+        Ok(imports) => imports,
+        Err(mut errors) => {
+            diagnostics.extend(util::distinct(&mut errors).iter().map(Diagnostic::from));
+            return Compilation { result: FileResult::Failed, diagnostics, files };
+        },
+    };
+
+    // Unresolved names are compared to these to find typos
+    let declarations: Vec<Declaration> = imports.iter().chain([&module])
+        .flat_map(|module| module.declarations.iter().cloned())
+        .collect();
+
+    let has_element = module.element.is_some();
+    let mut result = processor.process_module_with_imports(imports, module);
+
+    for warning in util::distinct(result.warnings_mut()) {
+        diagnostics.push(Diagnostic::from(&*warning));
+    }
+
+    for error in util::distinct(result.errors_mut()) {
+        diagnostics.push(suggest_argument(Diagnostic::from(&*error), error, processor, &declarations));
+    }
+
+    // Collected before formatting, which would only report the first unresolved name
+    let unresolved = result.element.as_ref().map(format::unresolved_symbols).unwrap_or_default();
+
+    for err in &unresolved {
+        diagnostics.push(suggest_name(Diagnostic::from(err), err, processor, &declarations));
+    }
+
+    let result = if !parse_errors.is_empty() || !result.errors.is_empty() || !unresolved.is_empty() {
+        FileResult::Failed
+    } else if !has_element {
+        FileResult::NoOutput
+    } else if let Some(element) = result.element {
+        match format::format_json(element, options) {
+            Ok(output) => FileResult::Output(output),
+            Err(err) => {
+                diagnostics.push(suggest_name(Diagnostic::from(&err), &err, processor, &declarations));
+                FileResult::Failed
+            },
+        }
+    } else {
+        FileResult::Failed // Processing failed without an error, only possible with a bug in the processor
+    };
+
+    Compilation { result, diagnostics, files }
+}
+
+fn with_suggestion<'a>(diagnostic: Diagnostic, name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Diagnostic {
+    match util::closest_name(name, candidates) {
+        Some(candidate) => diagnostic.with_help(format!("did you mean `{}`?", candidate)),
+        None => diagnostic,
+    }
+}
+
+// Unresolved functions and symbols can be typos of built-in functions or definitions
+fn suggest_name(diagnostic: Diagnostic, error: &format::Error, processor: &ElementProcessor<'_>, declarations: &[Declaration]) -> Diagnostic {
+    match error {
+        format::Error::UnresolvedSymbol(format::SymbolKind::Function | format::SymbolKind::Symbol, name, _) => {
+            let functions = processor.functions().iter().map(Function::name);
+            with_suggestion(diagnostic, name, functions.chain(declarations.iter().filter_map(Declaration::name)))
+        },
+        _ => diagnostic,
+    }
+}
+
+fn suggest_argument(diagnostic: Diagnostic, error: &ProcessError, processor: &ElementProcessor<'_>, declarations: &[Declaration]) -> Diagnostic {
+    match error {
+        ProcessError::UnknownArgument { function, name, .. } => {
+            let parameters = processor.functions().iter()
+                .filter(|candidate| candidate.name() == function)
+                .flat_map(|candidate| candidate.parameters().iter().map(Parameter::key));
+            let declared_parameters = declarations.iter()
+                .flat_map(|declaration| match declaration {
+                    Declaration::Function { name: declared, parameters, .. } if declared == function => parameters.as_slice(),
+                    _ => &[],
+                })
+                .map(String::as_str);
+
+            with_suggestion(diagnostic, name, parameters.chain(declared_parameters))
+        },
+        _ => diagnostic,
+    }
+}
@@ -0,0 +1,334 @@
+use crate::element::{Element, SplinePoint};
+use crate::parser::Span;
+use crate::parser::lexer::{LangToken, LangTokenType, TokenPos};
+use crate::processor::{ArgumentType, ElementProcessor, Function, OPEN_MAX, OPEN_MIN};
+
+// Turns density function JSON back into source elements. Objects are matched against the
+// functions and binary operators of the processor, so registry functions are decompiled as well.
+pub fn decompile(processor: &ElementProcessor<'_>, element: Element) -> Element {
+    match element {
+        Element::ArrayElement(values, span) => Element::ArrayElement(values.into_iter()
+            .map(|value| decompile(processor, value))
+            .collect(), span),
+        Element::ObjectElement(..) => {
+            let type_name = match object_type(&element) {
+                Some(type_name) => type_name,
+                None => return decompile_fields(processor, element),
+            };
+
+            let decompiled = match type_name.as_str() {
+                "minecraft:spline" => decompile_spline_function(processor, &element),
+                "minecraft:range_choice" => decompile_range_choice(processor, &element),
+                "minecraft:y_clamped_gradient" => decompile_gradient(processor, &element),
+                "minecraft:add" => decompile_lerp(processor, &element),
+                _ => None,
+            };
+
+            decompiled
+                .or_else(|| decompile_binary_operator(processor, &type_name, &element))
+                .or_else(|| decompile_function(processor, &type_name, &element))
+                .unwrap_or_else(|| decompile_fields(processor, element))
+        },
+        element => element,
+    }
+}
+
+// Keeps the object, but decompiles the values
+fn decompile_fields(processor: &ElementProcessor<'_>, element: Element) -> Element {
+    match element {
+        Element::ObjectElement(fields, span) => Element::ObjectElement(fields.into_iter()
+            .map(|(key, value)| (key, decompile(processor, value)))
+            .collect(), span),
+        element => element,
+    }
+}
+
+// The type of a density function object, with the default namespace added
+fn object_type(element: &Element) -> Option<String> {
+    match element.field("type") {
+        Some(Element::StringElement(type_name, _)) if type_name.contains(':') => Some(type_name.clone()),
+        Some(Element::StringElement(type_name, _)) => Some(format!("minecraft:{}", type_name)),
+        _ => None,
+    }
+}
+
+// Returns the fields of an object if it has exactly the given keys (besides "type")
+fn fields<'e, const N: usize>(element: &'e Element, keys: [&str; N]) -> Option<[&'e Element; N]> {
+    match element {
+        Element::ObjectElement(fields, _) if fields.len() == N + 1 => {
+            let mut values = Vec::with_capacity(N);
+
+            for key in keys {
+                values.push(element.field(key)?);
+            }
+
+            values.try_into().ok()
+        },
+        _ => None,
+    }
+}
+
+// The fields of a density function object of the given type
+fn typed_fields<'e, const N: usize>(element: &'e Element, type_name: &str, keys: [&str; N]) -> Option<[&'e Element; N]> {
+    if object_type(element).as_deref() == Some(type_name) {
+        fields(element, keys)
+    } else {
+        None
+    }
+}
+
+fn has_function(processor: &ElementProcessor<'_>, name: &str) -> bool {
+    processor.functions().iter().any(|function| function.name() == name)
+}
+
+fn token(token_type: LangTokenType, text: &str) -> LangToken {
+    LangToken::new(token_type, String::from(text), TokenPos::begin())
+}
+
+fn call(name: &str, arguments: Vec<Element>) -> Element {
+    Element::FunctionCallElement { receiver: None, name: String::from(name), arguments: Some(arguments), span: Span::none() }
+}
+
+// The operands of a commutative operator, in both orders
+fn operands<'e>(element: &'e Element, type_name: &str) -> Vec<(&'e Element, &'e Element)> {
+    typed_fields(element, type_name, ["argument1", "argument2"])
+        .map(|[left, right]| vec![(left, right), (right, left)])
+        .unwrap_or_default()
+}
+
+// add(mul(a, add(1, mul(cache_once(delta), -1))), mul(b, cache_once(delta))) => lerp(delta, a, b),
+// with the operands of add and mul in any order, as vanilla writes `mul(-1, cache_once(delta))`
+fn decompile_lerp(processor: &ElementProcessor<'_>, element: &Element) -> Option<Element> {
+    if !has_function(processor, "lerp") {
+        return None;
+    }
+
+    let [delta, a, b] = lerp_arguments(element)?;
+
+    Some(call("lerp", vec![
+        decompile(processor, delta.clone()),
+        decompile(processor, a.clone()),
+        decompile(processor, b.clone()),
+    ]))
+}
+
+fn lerp_arguments(element: &Element) -> Option<[&Element; 3]> {
+    for (left, right) in operands(element, "minecraft:add") {
+        for (a, one_minus_delta) in operands(left, "minecraft:mul") {
+            for (one, minus_delta) in operands(one_minus_delta, "minecraft:add") {
+                for (cached_delta, minus_one) in operands(minus_delta, "minecraft:mul") {
+                    for (b, cached_delta_2) in operands(right, "minecraft:mul") {
+                        if one.as_number() != Some(1.0) || minus_one.as_number() != Some(-1.0) || cached_delta != cached_delta_2 {
+                            continue;
+                        }
+
+                        if let Some([delta]) = typed_fields(cached_delta, "minecraft:cache_once", ["argument"]) {
+                            return Some([delta, a, b]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// { "type": "minecraft:spline", "spline": ... } => spline(coordinate) { ... }
+fn decompile_spline_function(processor: &ElementProcessor<'_>, element: &Element) -> Option<Element> {
+    let [spline] = fields(element, ["spline"])?;
+    decompile_spline(processor, spline)
+}
+
+fn decompile_spline(processor: &ElementProcessor<'_>, spline: &Element) -> Option<Element> {
+    let (coordinate, points) = match (spline, spline.field("coordinate"), spline.field("points")) {
+        (Element::ObjectElement(fields, _), Some(coordinate), Some(Element::ArrayElement(points, _))) if fields.len() == 2 => (coordinate, points),
+        _ => return None,
+    };
+
+    let mut result_points = Vec::new();
+
+    for point in points {
+        let (location, value, derivative) = match point {
+            Element::ObjectElement(fields, _) if fields.len() == 3 =>
+                (point.field("location")?, point.field("value")?, point.field("derivative")?),
+            _ => return None,
+        };
+
+        location.as_number()?;
+
+        // Values that are objects are nested splines
+        let value = match value {
+            Element::ObjectElement(..) => decompile_spline(processor, value)?,
+            value => value.clone(),
+        };
+
+        result_points.push(SplinePoint {
+            location: location.clone(),
+            value,
+            derivative: if derivative.as_number() == Some(0.0) { None } else { Some(derivative.clone()) },
+        });
+    }
+
+    Some(Element::SplineElement {
+        coordinate: Box::new(decompile(processor, coordinate.clone())),
+        points: result_points,
+        span: Span::none(),
+    })
+}
+
+// range_choice => if min <= input < max then a else b
+fn decompile_range_choice(processor: &ElementProcessor<'_>, element: &Element) -> Option<Element> {
+    if !has_function(processor, "range_choice") {
+        return None;
+    }
+
+    let [input, min, max, when_in_range, when_out_of_range] = fields(element,
+        ["input", "min_inclusive", "max_exclusive", "when_in_range", "when_out_of_range"])?;
+
+    // Bounds at the limits of range_choice are left out
+    let min = min.as_number().map(|value| (value > OPEN_MIN).then(|| min.clone()))?;
+    let max = max.as_number().map(|value| (value < OPEN_MAX).then(|| max.clone()))?;
+    let input = decompile(processor, input.clone());
+
+    let condition = match (min, max) {
+        (Some(min), Some(max)) => comparison(comparison(min, LangTokenType::LessEqual, "<=", input), LangTokenType::Less, "<", max),
+        (Some(min), None) => comparison(min, LangTokenType::LessEqual, "<=", input),
+        (None, Some(max)) => comparison(input, LangTokenType::Less, "<", max),
+        (None, None) => return None,
+    };
+
+    Some(Element::ConditionalElement {
+        condition: Box::new(condition),
+        when_true: Box::new(decompile(processor, when_in_range.clone())),
+        when_false: Box::new(decompile(processor, when_out_of_range.clone())),
+        span: Span::none(),
+    })
+}
+
+fn comparison(left: Element, token_type: LangTokenType, text: &str, right: Element) -> Element {
+    Element::BinaryElement { left: Box::new(left), operator: token(token_type, text), right: Box::new(right), span: Span::none() }
+}
+
+// y_clamped_gradient => y_gradient(from_y -> from_value, to_y -> to_value)
+fn decompile_gradient(processor: &ElementProcessor<'_>, element: &Element) -> Option<Element> {
+    if !has_function(processor, "y_gradient") {
+        return None;
+    }
+
+    let [from_y, to_y, from_value, to_value] = fields(element, ["from_y", "to_y", "from_value", "to_value"])?;
+
+    match (from_y, to_y) {
+        (Element::IntElement(from, _), Element::IntElement(to, _)) if from < to => {},
+        _ => return None,
+    }
+
+    let arrow = |y: &Element, value: &Element| comparison(y.clone(), LangTokenType::Arrow, "->", decompile(processor, value.clone()));
+    Some(call("y_gradient", vec![arrow(from_y, from_value), arrow(to_y, to_value)]))
+}
+
+fn decompile_binary_operator(processor: &ElementProcessor<'_>, type_name: &str, element: &Element) -> Option<Element> {
+    let operator = processor.binary_operators().iter()
+        .find(|operator| format!("{}:{}", operator.namespace(), operator.internal_name()) == type_name)?;
+    let [left, right] = fields(element, [operator.argument1_key(), operator.argument2_key()])?;
+
+    let left = decompile(processor, left.clone());
+    let right = decompile(processor, right.clone());
+
+    match (operator.name(), right) {
+        // a * -1 => -a
+        ("*", Element::FloatElement(-1.0, _)) => Some(Element::UnaryElement {
+            operator: token(LangTokenType::Minus, "-"),
+            operand: Box::new(left),
+            span: Span::none(),
+        }),
+        // a + -b => a - b
+        ("+", Element::UnaryElement { operator: minus, operand, .. }) => Some(Element::BinaryElement {
+            left: Box::new(left),
+            operator: minus,
+            right: operand,
+            span: Span::none(),
+        }),
+        (name, right) => Some(Element::BinaryElement {
+            left: Box::new(left),
+            operator: token(operator_token_type(name), name),
+            right: Box::new(right),
+            span: Span::none(),
+        }),
+    }
+}
+
+fn operator_token_type(operator: &str) -> LangTokenType {
+    match operator {
+        "+" => LangTokenType::Plus,
+        "-" => LangTokenType::Minus,
+        "*" => LangTokenType::Star,
+        "/" => LangTokenType::Slash,
+        _ => LangTokenType::None,
+    }
+}
+
+fn decompile_function(processor: &ElementProcessor<'_>, type_name: &str, element: &Element) -> Option<Element> {
+    processor.functions().iter()
+        .filter(|function| function.custom().is_none() && function.type_name() == type_name)
+        .find_map(|function| decompile_call(processor, function, element))
+}
+
+fn decompile_call(processor: &ElementProcessor<'_>, function: &Function, element: &Element) -> Option<Element> {
+    let fields = match element {
+        Element::ObjectElement(fields, _) => fields,
+        _ => return None,
+    };
+
+    // Every field has to be an argument of the function
+    let is_parameter = |key: &Element| matches!(key, Element::StringElement(key, _)
+        if key == "type" || function.parameters().iter().any(|parameter| parameter.key() == key));
+
+    if !fields.iter().all(|(key, _)| is_parameter(key)) {
+        return None;
+    }
+
+    let mut arguments = Vec::new();
+
+    for parameter in function.parameters() {
+        let argument = match (element.field(parameter.key()), parameter.default()) {
+            (Some(argument), _) => argument.clone(),
+            (None, Some(default)) => default.clone(),
+            (None, None) => return None,
+        };
+
+        if !parameter.argument_type().accepts(&argument) {
+            return None;
+        }
+
+        arguments.push(argument);
+    }
+
+    // Trailing arguments with the default value can be left out
+    while let Some(argument) = arguments.last() {
+        let is_default = function.parameters()[arguments.len() - 1].default().is_some_and(|default| argument == default
+            || (argument.as_number().is_some() && argument.as_number() == default.as_number()));
+
+        if !is_default {
+            break;
+        }
+
+        arguments.pop();
+    }
+
+    let mut arguments: Vec<Element> = arguments.into_iter()
+        .map(|argument| decompile(processor, argument))
+        .collect();
+
+    let method = function.allow_method_syntax() && !arguments.is_empty()
+        && function.parameters()[0].argument_type() == ArgumentType::DensityFunction;
+
+    let receiver = if method { Some(Box::new(arguments.remove(0))) } else { None };
+
+    Some(Element::FunctionCallElement {
+        receiver,
+        name: function.name().to_owned(),
+        arguments: Some(arguments),
+        span: Span::none(),
+    })
+}
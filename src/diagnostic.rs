@@ -0,0 +1,199 @@
+use std::fmt::{Display, Formatter, Write};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+use crate::format;
+use crate::parser::{FileId, ParseError, SourceFiles, Span};
+use crate::processor::{ProcessError, ProcessWarning};
+use crate::processor::registry::RegistryError;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+impl Severity {
+    fn color(&self) -> &'static str {
+        match self {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+}
+
+// An error or warning as shown to the user, rendered like rustc does:
+//
+// error[E0201]: Unresolved function 'cache_2d_'
+//  --> input/overworld/offset.json:3:5
+//   |
+// 3 |     cache_2d_(x)
+//   |     ^^^^^^^^^^^^
+//   = help: did you mean `cache_2d`?
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: &'static str,
+    message: String,
+    span: Option<Span>,
+    notes: Vec<String>,
+    help: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic {
+            severity, code, message, span, notes: Vec::new(), help: None,
+        }
+    }
+
+    pub fn error(code: &'static str, message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic::new(Severity::Error, code, message, span)
+    }
+
+    pub fn warning(code: &'static str, message: String, span: Option<Span>) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, code, message, span)
+    }
+
+    pub fn with_note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Diagnostic {
+        self.help = Some(help);
+        self
+    }
+
+    // Parse errors only know their position, not the file they are in
+    pub fn from_parse_error(error: &ParseError, file: FileId) -> Diagnostic {
+        Diagnostic::error(error.code(), error.message(), error.span(file))
+    }
+
+    pub fn severity(&self) -> Severity { self.severity }
+    pub fn code(&self) -> &'static str { self.code }
+    pub fn message(&self) -> &str { &self.message }
+    pub fn span(&self) -> Option<Span> { self.span }
+    pub fn notes(&self) -> &[String] { &self.notes }
+    pub fn help(&self) -> Option<&str> { self.help.as_deref() }
+
+    pub fn render(&self, files: &SourceFiles, color: bool) -> String {
+        let paint = |style: &str, text: &str| if color { format!("{}{}{}", style, text, RESET) } else { text.to_owned() };
+        let mut output = String::new();
+
+        let header = format!("{}[{}]", self.severity, self.code);
+        let _ = writeln!(output, "{}{}", paint(self.severity.color(), &header), paint(BOLD, &format!(": {}", self.message)));
+
+        let span = self.span.filter(|span| !span.is_none());
+        let line_number = span.map_or(0, |span| span.start.line + 1);
+        let gutter = " ".repeat(line_number.to_string().len());
+
+        if let Some(span) = span {
+            let path = files.path(span.file).map_or_else(|| String::from("<unknown>"), display_path);
+            let _ = writeln!(output, "{}{} {}:{}:{}", gutter, paint(BLUE, "-->"), path, line_number, span.start.column + 1);
+
+            if let Some(line) = files.source(span.file).and_then(|source| source.lines().nth(span.start.line as usize)) {
+                let bar = paint(BLUE, "|");
+                let _ = writeln!(output, "{} {}", gutter, bar);
+                let _ = writeln!(output, "{} {} {}", paint(BLUE, &line_number.to_string()), bar, line);
+                let _ = writeln!(output, "{} {} {}", gutter, bar, paint(self.severity.color(), &underline(line, span)));
+            }
+        }
+
+        for note in &self.notes {
+            let _ = writeln!(output, "{} {} note: {}", gutter, paint(BLUE, "="), note);
+        }
+
+        if let Some(help) = &self.help {
+            let _ = writeln!(output, "{} {} help: {}", gutter, paint(BLUE, "="), help);
+        }
+
+        output
+    }
+}
+
+// Spaces up to the start of the span (keeping tabs, so the carets line up), then carets up to its
+// end. Spans over multiple lines are underlined until the end of the first line.
+fn underline(line: &str, span: Span) -> String {
+    let start = span.start.column.max(0) as usize;
+    let line_length = line.chars().count();
+
+    let end = if span.end.line == span.start.line { span.end.column.max(0) as usize } else { line_length };
+    let carets = end.min(line_length).saturating_sub(start).max(1);
+
+    let mut output: String = line.chars().take(start).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    output.push_str(&"^".repeat(carets));
+    output
+}
+
+// Paths below the working directory are shown relative to it
+fn display_path(path: &Path) -> String {
+    let relative = std::env::current_dir().ok()
+        .filter(|_| path.is_absolute())
+        .and_then(|directory| pathdiff::diff_paths(path, directory))
+        .filter(|relative| !relative.starts_with(".."));
+
+    relative.unwrap_or_else(|| PathBuf::from(path)).to_string_lossy().into_owned()
+}
+
+// Colors are only used when writing to a terminal, and can be turned off with NO_COLOR
+pub fn use_color(stream: &impl IsTerminal) -> bool {
+    stream.is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+impl From<&ProcessError> for Diagnostic {
+    fn from(error: &ProcessError) -> Self {
+        let diagnostic = Diagnostic::error(error.code(), error.to_string(), error.span());
+
+        match error {
+            ProcessError::DivisionByZero(_) => diagnostic.with_help(String::from("divide by a number other than 0")),
+            ProcessError::RecursiveFunction(..) =>
+                diagnostic.with_note(String::from("functions are inlined where they are called, so they can't call themselves")),
+            ProcessError::UnorderedSplinePoints { .. } => diagnostic.with_help(String::from("sort the points by location")),
+            ProcessError::EmptyGradient { .. } => diagnostic.with_help(String::from("swap the two points")),
+            ProcessError::ImportNotFound { .. } =>
+                diagnostic.with_note(String::from("import paths are relative to the input folder or a prelude folder")),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&ProcessWarning> for Diagnostic {
+    fn from(warning: &ProcessWarning) -> Self {
+        Diagnostic::warning(warning.code(), warning.to_string(), warning.span())
+    }
+}
+
+impl From<&format::Error> for Diagnostic {
+    fn from(error: &format::Error) -> Self {
+        let diagnostic = Diagnostic::error(error.code(), error.to_string(), error.span());
+
+        match error {
+            format::Error::UnresolvedSymbol(format::SymbolKind::Symbol, ..) =>
+                diagnostic.with_note(String::from("names have to be defined with `let` or be functions without arguments")),
+            format::Error::NotSimpleElement(_) => diagnostic.with_note(String::from("this is a bug in the compiler")),
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&RegistryError> for Diagnostic {
+    fn from(error: &RegistryError) -> Self {
+        Diagnostic::error(error.code(), error.to_string(), error.span())
+    }
+}
@@ -0,0 +1,109 @@
+use std::fmt::{Display, Formatter};
+
+use crate::element::Element;
+use crate::format;
+
+// Values longer than this are cut off when showing differences
+const MAX_PREVIEW_LENGTH: usize = 80;
+
+// A difference between two JSON values, at a path like `argument2.argument1.xz_scale` or
+// `points[2].value`. A missing value means that the key or array index only exists on one side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Difference {
+    path: String,
+    left: Option<Element>,
+    right: Option<Element>,
+}
+
+impl Difference {
+    pub fn path(&self) -> &str { &self.path }
+    pub fn left(&self) -> Option<&Element> { self.left.as_ref() }
+    pub fn right(&self) -> Option<&Element> { self.right.as_ref() }
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "(root)" } else { &self.path };
+        write!(f, "{}: {} != {}", path, preview(self.left.as_ref()), preview(self.right.as_ref()))
+    }
+}
+
+// Short JSON for showing a value
+fn preview(element: Option<&Element>) -> String {
+    let element = match element {
+        Some(element) => element,
+        None => return String::from("(missing)"),
+    };
+
+    let json = format::format_json(element.clone(), format::Options::new(format::Layout::Compressed))
+        .unwrap_or_else(|_| format!("{:?}", element));
+
+    if json.chars().count() > MAX_PREVIEW_LENGTH {
+        json.chars().take(MAX_PREVIEW_LENGTH - 3).collect::<String>() + "..."
+    } else {
+        json
+    }
+}
+
+fn object_key(key: &Element) -> String {
+    match key {
+        Element::StringElement(key, _) => key.clone(),
+        key => preview(Some(key)),
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_owned() } else { format!("{}.{}", path, key) }
+}
+
+// Compares two JSON values, ignoring the order of object keys and whether numbers are written
+// as ints or floats. Numbers that differ by at most `tolerance` times their size are equal.
+pub fn diff(left: &Element, right: &Element, tolerance: f64) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_at(String::new(), left, right, tolerance, &mut differences);
+    differences
+}
+
+fn diff_at(path: String, left: &Element, right: &Element, tolerance: f64, differences: &mut Vec<Difference>) {
+    match (left, right) {
+        (Element::ObjectElement(left_fields, _), Element::ObjectElement(right_fields, _)) => {
+            for (key, left_value) in left_fields {
+                let key = object_key(key);
+
+                match right_fields.iter().find(|(right_key, _)| object_key(right_key) == key) {
+                    Some((_, right_value)) => diff_at(join_path(&path, &key), left_value, right_value, tolerance, differences),
+                    None => differences.push(Difference { path: join_path(&path, &key), left: Some(left_value.clone()), right: None }),
+                }
+            }
+
+            for (key, right_value) in right_fields {
+                let key = object_key(key);
+
+                if !left_fields.iter().any(|(left_key, _)| object_key(left_key) == key) {
+                    differences.push(Difference { path: join_path(&path, &key), left: None, right: Some(right_value.clone()) });
+                }
+            }
+        },
+        (Element::ArrayElement(left_values, _), Element::ArrayElement(right_values, _)) => {
+            for i in 0..left_values.len().max(right_values.len()) {
+                let index_path = format!("{}[{}]", path, i);
+
+                match (left_values.get(i), right_values.get(i)) {
+                    (Some(left_value), Some(right_value)) => diff_at(index_path, left_value, right_value, tolerance, differences),
+                    (left_value, right_value) =>
+                        differences.push(Difference { path: index_path, left: left_value.cloned(), right: right_value.cloned() }),
+                }
+            }
+        },
+        _ => {
+            let equal = match (left.as_number(), right.as_number()) {
+                (Some(left), Some(right)) => (left - right).abs() <= tolerance * left.abs().max(right.abs()) || left == right,
+                _ => left == right,
+            };
+
+            if !equal {
+                differences.push(Difference { path, left: Some(left.clone()), right: Some(right.clone()) });
+            }
+        },
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::{Debug, Formatter};
 
+use crate::parser::Span;
 use crate::parser::lexer::LangToken;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -16,58 +17,106 @@ pub enum ElementKind {
     Array,
     Object,
 
+    Unary,
     Binary,
     FunctionCall,
+    NamedArgument,
+    Conditional,
+    Spline,
+
+    Error,
 }
 
-#[derive(Clone, PartialEq)]
+// Every element knows the part of the source it was parsed from, for error messages.
+// Elements created during processing take the span of the element they replace.
+#[derive(Clone)]
 pub enum Element {
-    NullElement,
+    NullElement(Span),
 
-    BooleanElement(bool),
-    IntElement(i32),
-    FloatElement(f64),
-    StringElement(String),
-    NameElement(String),
+    BooleanElement(bool, Span),
+    IntElement(i32, Span),
+    FloatElement(f64, Span),
+    StringElement(String, Span),
+    NameElement(String, Span),
 
-    ArrayElement(Vec<Element>),
-    ObjectElement(Vec<(Element, Element)>), // Key-value pair for object
+    ArrayElement(Vec<Element>, Span),
+    ObjectElement(Vec<(Element, Element)>, Span), // Key-value pair for object
 
     // Additional (not JSON)
+    UnaryElement {
+        operator: LangToken,
+        operand: Box<Element>,
+        span: Span,
+    },
+
     BinaryElement {
         left: Box<Element>,
         operator: LangToken,
         right: Box<Element>,
+        span: Span,
     },
 
     FunctionCallElement {
         receiver: Option<Box<Element>>,
         name: String,
         arguments: Option<Vec<Element>>,
+        span: Span,
     },
+
+    // Only valid as an argument of a function call
+    NamedArgumentElement {
+        name: String,
+        value: Box<Element>,
+        span: Span,
+    },
+
+    // `if condition then when_true else when_false`
+    ConditionalElement {
+        condition: Box<Element>,
+        when_true: Box<Element>,
+        when_false: Box<Element>,
+        span: Span,
+    },
+
+    // `spline(coordinate) { location: value ' derivative, ... }`
+    SplineElement {
+        coordinate: Box<Element>,
+        points: Vec<SplinePoint>,
+        span: Span,
+    },
+
+    // Placeholder for a part of the source that could not be parsed, the error was already reported
+    ErrorElement(Span),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SplinePoint {
+    pub location: Element,
+    pub value: Element,
+    pub derivative: Option<Element>, // 0 if not given
 }
 
 #[macro_export]
 macro_rules! string_element {
     ($string:literal) => {
-        Element::StringElement(String::from($string))
+        Element::StringElement(String::from($string), $crate::parser::Span::none())
     };
     ($string:expr) => {
-        Element::StringElement($string)
+        Element::StringElement($string, $crate::parser::Span::none())
     }
 }
 
 #[macro_export]
 macro_rules! object_element {
     [$($name:expr => $element:expr),*] => {
-        Element::ObjectElement(vec![$(($name, $element)),*])
+        Element::ObjectElement(vec![$(($name, $element)),*], $crate::parser::Span::none())
     }
 }
 
 #[macro_export]
 macro_rules! array_element {
     [$($element:expr),*] => {
-        Element::ArrayElement(vec![$($element),*])
+        Element::ArrayElement(vec![$($element),*], $crate::parser::Span::none())
     }
 }
 
@@ -80,16 +129,163 @@ impl From<Element> for ElementKind {
 impl Element {
     pub fn kind(&self) -> ElementKind {
         match self {
-            Element::NullElement => ElementKind::Null,
-            Element::BooleanElement(_) => ElementKind::Boolean,
-            Element::IntElement(_) => ElementKind::Int,
-            Element::FloatElement(_) => ElementKind::Float,
-            Element::StringElement(_) => ElementKind::String,
-            Element::NameElement(_) => ElementKind::Name,
-            Element::ArrayElement(_) => ElementKind::Array,
-            Element::ObjectElement(_) => ElementKind::Object,
+            Element::NullElement(_) => ElementKind::Null,
+            Element::BooleanElement(..) => ElementKind::Boolean,
+            Element::IntElement(..) => ElementKind::Int,
+            Element::FloatElement(..) => ElementKind::Float,
+            Element::StringElement(..) => ElementKind::String,
+            Element::NameElement(..) => ElementKind::Name,
+            Element::ArrayElement(..) => ElementKind::Array,
+            Element::ObjectElement(..) => ElementKind::Object,
+            Element::UnaryElement { .. } => ElementKind::Unary,
             Element::BinaryElement { .. } => ElementKind::Binary,
             Element::FunctionCallElement { .. } => ElementKind::FunctionCall,
+            Element::NamedArgumentElement { .. } => ElementKind::NamedArgument,
+            Element::ConditionalElement { .. } => ElementKind::Conditional,
+            Element::SplineElement { .. } => ElementKind::Spline,
+            Element::ErrorElement(_) => ElementKind::Error,
+        }
+    }
+
+    // Returns the value of the field with a string key in an object element
+    pub fn field(&self, key: &str) -> Option<&Element> {
+        match self {
+            Element::ObjectElement(fields, _) => fields.iter()
+                .find(|(field_key, _)| matches!(field_key, Element::StringElement(name, _) if name == key))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Element::IntElement(value, _) => Some(*value as f64),
+            Element::FloatElement(value, _) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Element::NullElement(span)
+            | Element::BooleanElement(_, span)
+            | Element::IntElement(_, span)
+            | Element::FloatElement(_, span)
+            | Element::StringElement(_, span)
+            | Element::NameElement(_, span)
+            | Element::ArrayElement(_, span)
+            | Element::ObjectElement(_, span)
+            | Element::UnaryElement { span, .. }
+            | Element::BinaryElement { span, .. }
+            | Element::FunctionCallElement { span, .. }
+            | Element::NamedArgumentElement { span, .. }
+            | Element::ConditionalElement { span, .. }
+            | Element::SplineElement { span, .. }
+            | Element::ErrorElement(span) => *span,
+        }
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            Element::NullElement(span)
+            | Element::BooleanElement(_, span)
+            | Element::IntElement(_, span)
+            | Element::FloatElement(_, span)
+            | Element::StringElement(_, span)
+            | Element::NameElement(_, span)
+            | Element::ArrayElement(_, span)
+            | Element::ObjectElement(_, span)
+            | Element::UnaryElement { span, .. }
+            | Element::BinaryElement { span, .. }
+            | Element::FunctionCallElement { span, .. }
+            | Element::NamedArgumentElement { span, .. }
+            | Element::ConditionalElement { span, .. }
+            | Element::SplineElement { span, .. }
+            | Element::ErrorElement(span) => span,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Element {
+        *self.span_mut() = span;
+        self
+    }
+
+    // Gives `span` to this element and every element inside it that has no span yet,
+    // so that the output of a built-in function points at the call
+    pub fn inherit_span(mut self, span: Span) -> Element {
+        self.inherit_span_in_place(span);
+        self
+    }
+
+    fn inherit_span_in_place(&mut self, span: Span) {
+        if self.span().is_none() {
+            *self.span_mut() = span;
+        }
+
+        match self {
+            Element::ArrayElement(values, _) => values.iter_mut().for_each(|value| value.inherit_span_in_place(span)),
+            Element::ObjectElement(fields, _) => fields.iter_mut().for_each(|(key, value)| {
+                key.inherit_span_in_place(span);
+                value.inherit_span_in_place(span);
+            }),
+            _ => {},
+        }
+    }
+
+    // Whether a part of this element could not be parsed
+    pub fn contains_error(&self) -> bool {
+        match self {
+            Element::ErrorElement(_) => true,
+            Element::ArrayElement(values, _) => values.iter().any(Element::contains_error),
+            Element::ObjectElement(fields, _) => fields.iter().any(|(key, value)| key.contains_error() || value.contains_error()),
+            Element::UnaryElement { operand, .. } => operand.contains_error(),
+            Element::BinaryElement { left, right, .. } => left.contains_error() || right.contains_error(),
+            Element::FunctionCallElement { receiver, arguments, .. } =>
+                receiver.as_ref().is_some_and(|receiver| receiver.contains_error())
+                    || arguments.as_ref().is_some_and(|arguments| arguments.iter().any(Element::contains_error)),
+            Element::NamedArgumentElement { value, .. } => value.contains_error(),
+            Element::ConditionalElement { condition, when_true, when_false, .. } =>
+                condition.contains_error() || when_true.contains_error() || when_false.contains_error(),
+            Element::SplineElement { coordinate, points, .. } => coordinate.contains_error() || points.iter().any(|point|
+                point.location.contains_error() || point.value.contains_error()
+                    || point.derivative.as_ref().is_some_and(Element::contains_error)),
+            _ => false,
+        }
+    }
+}
+
+// Spans are ignored, elements are equal if they were written differently
+impl PartialEq for Element {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Element::NullElement(_), Element::NullElement(_)) => true,
+            (Element::BooleanElement(a, _), Element::BooleanElement(b, _)) => a == b,
+            (Element::IntElement(a, _), Element::IntElement(b, _)) => a == b,
+            (Element::FloatElement(a, _), Element::FloatElement(b, _)) => a == b,
+            (Element::StringElement(a, _), Element::StringElement(b, _)) => a == b,
+            (Element::NameElement(a, _), Element::NameElement(b, _)) => a == b,
+            (Element::ArrayElement(a, _), Element::ArrayElement(b, _)) => a == b,
+            (Element::ObjectElement(a, _), Element::ObjectElement(b, _)) => a == b,
+            (Element::UnaryElement { operator: a_operator, operand: a_operand, .. },
+                Element::UnaryElement { operator: b_operator, operand: b_operand, .. }) =>
+                a_operator.text() == b_operator.text() && a_operand == b_operand,
+            (Element::BinaryElement { left: a_left, operator: a_operator, right: a_right, .. },
+                Element::BinaryElement { left: b_left, operator: b_operator, right: b_right, .. }) =>
+                a_operator.text() == b_operator.text() && a_left == b_left && a_right == b_right,
+            (Element::FunctionCallElement { receiver: a_receiver, name: a_name, arguments: a_arguments, .. },
+                Element::FunctionCallElement { receiver: b_receiver, name: b_name, arguments: b_arguments, .. }) =>
+                a_name == b_name && a_receiver == b_receiver && a_arguments == b_arguments,
+            (Element::NamedArgumentElement { name: a_name, value: a_value, .. },
+                Element::NamedArgumentElement { name: b_name, value: b_value, .. }) =>
+                a_name == b_name && a_value == b_value,
+            (Element::ConditionalElement { condition: a_condition, when_true: a_true, when_false: a_false, .. },
+                Element::ConditionalElement { condition: b_condition, when_true: b_true, when_false: b_false, .. }) =>
+                a_condition == b_condition && a_true == b_true && a_false == b_false,
+            (Element::SplineElement { coordinate: a_coordinate, points: a_points, .. },
+                Element::SplineElement { coordinate: b_coordinate, points: b_points, .. }) =>
+                a_coordinate == b_coordinate && a_points == b_points,
+            (Element::ErrorElement(_), Element::ErrorElement(_)) => true,
+            _ => false,
         }
     }
 }
@@ -97,13 +293,13 @@ impl Element {
 impl Debug for Element {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Element::NullElement => f.write_str("null"),
-            Element::BooleanElement(value) => write!(f, "{}", value),
-            Element::IntElement(value) => write!(f, "{}", value),
-            Element::FloatElement(value) => write!(f, "{}", value),
-            Element::StringElement(value) => write!(f, "\"{}\"", value),
-            Element::NameElement(value) => write!(f, "{}", value),
-            Element::ArrayElement(values) => {
+            Element::NullElement(_) => f.write_str("null"),
+            Element::BooleanElement(value, _) => write!(f, "{}", value),
+            Element::IntElement(value, _) => write!(f, "{}", value),
+            Element::FloatElement(value, _) => write!(f, "{}", value),
+            Element::StringElement(value, _) => write!(f, "\"{}\"", value),
+            Element::NameElement(value, _) => write!(f, "{}", value),
+            Element::ArrayElement(values, _) => {
                 if values.is_empty() {
                     return write!(f, "[]");
                 }
@@ -120,7 +316,7 @@ impl Debug for Element {
 
                 f.write_str(" ]")
             },
-            Element::ObjectElement(fields) => {
+            Element::ObjectElement(fields, _) => {
                 if fields.is_empty() {
                     return write!(f, "{{}}");
                 }
@@ -137,10 +333,12 @@ impl Debug for Element {
 
                 f.write_str(" }")
             },
-            Element::BinaryElement { left, operator, right } =>
+            Element::UnaryElement { operator, operand, .. } =>
+                write!(f, "{0}{1:?}", operator.text(), *operand),
+            Element::BinaryElement { left, operator, right, .. } =>
                 write!(f, "({0:?} {1} {2:?})", *left, operator.text(), *right),
 
-            Element::FunctionCallElement { receiver, name, arguments } => {
+            Element::FunctionCallElement { receiver, name, arguments, .. } => {
                 if let Some(receiver) = receiver {
                     write!(f, "{:?}.", *receiver)?;
                 }
@@ -163,6 +361,28 @@ impl Debug for Element {
 
                 Ok(())
             },
+
+            Element::NamedArgumentElement { name, value, .. } => write!(f, "{0}: {1:?}", name, *value),
+            Element::ConditionalElement { condition, when_true, when_false, .. } =>
+                write!(f, "(if {0:?} then {1:?} else {2:?})", *condition, *when_true, *when_false),
+            Element::SplineElement { coordinate, points, .. } => {
+                write!(f, "spline({:?}) {{ ", *coordinate)?;
+
+                for (i, point) in points.iter().enumerate() {
+                    write!(f, "{:?}: {:?}", point.location, point.value)?;
+
+                    if let Some(derivative) = &point.derivative {
+                        write!(f, " ' {:?}", derivative)?;
+                    }
+
+                    if i < points.len() - 1 {
+                        f.write_str(", ")?;
+                    }
+                }
+
+                f.write_str(" }")
+            },
+            Element::ErrorElement(_) => f.write_str("<error>"),
         }
     }
 }
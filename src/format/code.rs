@@ -0,0 +1,364 @@
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+use crate::parser::{MAX_DEPTH, ParseError};
+use crate::parser::lexer::{LangLexer, LangToken, LangTokenType, TokenPos};
+
+const INDENTATION: &str = "  ";
+const MAX_WIDTH: usize = 100;
+
+// A token with the number of line breaks before it in the source
+struct Token {
+    token: LangToken,
+    lines_before: i32,
+}
+
+impl Token {
+    fn is_comment(&self) -> bool {
+        self.token.token_type() == LangTokenType::Comment
+    }
+
+    fn is_line_comment(&self) -> bool {
+        self.is_comment() && self.token.text().starts_with("//")
+    }
+}
+
+enum Node {
+    Token(Token),
+    Group(Group),
+}
+
+// Tokens between brackets, split at the commas
+struct Group {
+    open: Token,
+    items: Vec<Item>,
+    close: Token,
+}
+
+// Comments at the end of an item are written after its comma
+struct Item {
+    nodes: Vec<Node>,
+    trailing_comments: Vec<Token>,
+}
+
+impl Item {
+    fn new(mut nodes: Vec<Node>) -> Item {
+        let mut trailing_comments = Vec::new();
+
+        while let Some(Node::Token(token)) = nodes.last() {
+            if !token.is_comment() {
+                break;
+            }
+
+            match nodes.pop() {
+                Some(Node::Token(token)) => trailing_comments.insert(0, token),
+                _ => unreachable!(),
+            }
+        }
+
+        Item { nodes, trailing_comments }
+    }
+}
+
+// Re-emits source code with consistent indentation and spacing. Comments and single blank
+// lines are kept, argument lists and arrays are wrapped if they don't fit on one line.
+pub fn format_code(source: &str) -> Result<String, ParseError> {
+    let mut tokens = scan_tokens(source)?.into_iter().peekable();
+    let nodes = parse_nodes(&mut tokens, &[], 0)?;
+
+    let mut printer = Printer::new();
+    printer.write_nodes(&nodes, false);
+    printer.newline();
+    Ok(printer.output)
+}
+
+fn scan_tokens(source: &str) -> Result<Vec<Token>, ParseError> {
+    let mut lexer = LangLexer::new_with_comments(source);
+    let mut tokens = Vec::new();
+    let mut end_line = 0;
+
+    loop {
+        let token = lexer.scan_token()?;
+
+        if token.token_type() == LangTokenType::Eof {
+            return Ok(tokens);
+        }
+
+        // Block comments and strings can span multiple lines
+        let lines_before = token.pos().line - end_line;
+        end_line = token.pos().line + token.text().matches('\n').count() as i32;
+        tokens.push(Token { token, lines_before });
+    }
+}
+
+fn closing_bracket(token_type: LangTokenType) -> Option<LangTokenType> {
+    match token_type {
+        LangTokenType::ObjectBegin => Some(LangTokenType::ObjectEnd),
+        LangTokenType::ArrayBegin => Some(LangTokenType::ArrayEnd),
+        LangTokenType::GroupBegin => Some(LangTokenType::GroupEnd),
+        _ => None,
+    }
+}
+
+// Reads nodes until one of the `until` tokens, which is not consumed. `depth` is the number of
+// enclosing groups.
+fn parse_nodes(tokens: &mut Peekable<IntoIter<Token>>, until: &[LangTokenType], depth: usize) -> Result<Vec<Node>, ParseError> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.peek() {
+        let token_type = token.token.token_type();
+
+        if until.contains(&token_type) {
+            break;
+        }
+
+        let token = tokens.next().expect("peeked token");
+
+        match token_type {
+            LangTokenType::ObjectBegin | LangTokenType::ArrayBegin | LangTokenType::GroupBegin if depth >= MAX_DEPTH =>
+                return Err(ParseError::TooDeeplyNested(*token.token.pos())),
+            LangTokenType::ObjectBegin | LangTokenType::ArrayBegin | LangTokenType::GroupBegin =>
+                nodes.push(Node::Group(parse_group(tokens, token, depth + 1)?)),
+            LangTokenType::ObjectEnd | LangTokenType::ArrayEnd | LangTokenType::GroupEnd =>
+                return Err(ParseError::UnexpectedToken(*token.token.pos(), token.token)),
+            _ => nodes.push(Node::Token(token)),
+        }
+    }
+
+    Ok(nodes)
+}
+
+fn parse_group(tokens: &mut Peekable<IntoIter<Token>>, open: Token, depth: usize) -> Result<Group, ParseError> {
+    let close_type = closing_bracket(open.token.token_type()).expect("opening bracket");
+    let mut items: Vec<Item> = Vec::new();
+
+    loop {
+        let mut item = Item::new(parse_nodes(tokens, &[LangTokenType::Comma, close_type], depth)?);
+        let separator = tokens.next().ok_or(ParseError::UnexpectedEof)?;
+
+        // Comments after the last comma belong to the item before it
+        if item.nodes.is_empty() {
+            if let Some(previous) = items.last_mut() {
+                previous.trailing_comments.append(&mut item.trailing_comments);
+            } else if !item.trailing_comments.is_empty() {
+                items.push(item);
+            }
+        } else {
+            items.push(item);
+        }
+
+        if separator.token.token_type() == close_type {
+            return Ok(Group { open, items, close: separator });
+        }
+
+        // Comments on the same line as the comma
+        while tokens.peek().is_some_and(|token| token.is_comment() && token.lines_before == 0) {
+            let comment = tokens.next().expect("peeked token");
+
+            match items.last_mut() {
+                Some(item) => item.trailing_comments.push(comment),
+                None => return Err(ParseError::UnexpectedToken(*separator.token.pos(), separator.token)),
+            }
+        }
+    }
+}
+
+fn needs_space(previous: LangTokenType, unary_minus: bool, next: LangTokenType) -> bool {
+    match (previous, next) {
+        (_, LangTokenType::Comma | LangTokenType::Colon | LangTokenType::Semicolon | LangTokenType::Dot
+            | LangTokenType::GroupEnd | LangTokenType::ArrayEnd) => false,
+        (LangTokenType::Dot | LangTokenType::GroupBegin | LangTokenType::ArrayBegin, _) => false,
+        (LangTokenType::ObjectBegin, LangTokenType::ObjectEnd) => false,
+        (LangTokenType::Minus, _) if unary_minus => false,
+        (LangTokenType::Name | LangTokenType::Spline, LangTokenType::GroupBegin) => false, // Function calls
+        _ => true,
+    }
+}
+
+// A minus is unary if there is no value before it
+fn ends_value(token_type: LangTokenType) -> bool {
+    matches!(token_type, LangTokenType::Name | LangTokenType::LiteralString | LangTokenType::LiteralNumber
+        | LangTokenType::LiteralTrue | LangTokenType::LiteralFalse | LangTokenType::LiteralNull
+        | LangTokenType::GroupEnd | LangTokenType::ArrayEnd | LangTokenType::ObjectEnd)
+}
+
+fn can_be_flat(group: &Group) -> bool {
+    if group.open.token.token_type() == LangTokenType::ObjectBegin && !group.items.is_empty() {
+        return false; // Objects and splines are always written over multiple lines
+    }
+
+    group.items.iter().all(|item| {
+        item.trailing_comments.iter().all(|comment| !comment.is_line_comment() && comment.lines_before == 0)
+            && item.nodes.iter().all(|node| match node {
+                Node::Token(token) => !token.is_comment() || (!token.is_line_comment() && token.lines_before == 0),
+                Node::Group(group) => can_be_flat(group),
+            })
+    })
+}
+
+struct Printer {
+    output: String,
+    indent: usize,
+    line_start: bool,
+    previous: Option<LangTokenType>,
+    unary_minus: bool,
+    needs_newline: bool,
+    comment_line: bool, // The last token was a comment on its own line
+}
+
+impl Printer {
+    fn new() -> Printer {
+        Printer {
+            output: String::new(),
+            indent: 0,
+            line_start: true,
+            previous: None,
+            unary_minus: false,
+            needs_newline: false,
+            comment_line: false,
+        }
+    }
+
+    fn column(&self) -> usize {
+        self.output.len() - self.output.rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn newline(&mut self) {
+        if !self.line_start {
+            self.output.push('\n');
+            self.line_start = true;
+        }
+
+        self.needs_newline = false;
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn write_token(&mut self, token: &Token) {
+        // What follows a comment on its own line stays on the next line
+        if self.comment_line && token.lines_before > 0 {
+            self.needs_newline = true;
+        }
+
+        // Comments can follow on the same line
+        if self.needs_newline && !(token.is_comment() && token.lines_before == 0) {
+            self.newline();
+        }
+
+        if token.is_comment() && token.lines_before > 0 {
+            self.newline(); // Comments on their own line stay there
+        }
+
+        if self.line_start && token.lines_before > 1 {
+            self.blank_line();
+        }
+
+        let own_line = self.line_start;
+        self.write(&token.token);
+        self.needs_newline = token.is_line_comment() || (token.is_comment() && self.needs_newline);
+        self.comment_line = token.is_comment() && own_line;
+    }
+
+    fn write(&mut self, token: &LangToken) {
+        let token_type = token.token_type();
+
+        if self.line_start {
+            for _ in 0..self.indent {
+                self.output.push_str(INDENTATION);
+            }
+        } else if let Some(previous) = self.previous {
+            if needs_space(previous, self.unary_minus, token_type) {
+                self.output.push(' ');
+            }
+        }
+
+        if token_type == LangTokenType::LiteralString {
+            self.output.push('"');
+            self.output.push_str(token.text());
+            self.output.push('"');
+        } else {
+            self.output.push_str(token.text());
+        }
+
+        self.unary_minus = token_type == LangTokenType::Minus && !self.previous.is_some_and(ends_value);
+        self.previous = Some(token_type);
+        self.line_start = false;
+    }
+
+    fn write_comma(&mut self) {
+        self.write(&LangToken::new(LangTokenType::Comma, String::from(","), TokenPos::begin()));
+    }
+
+    // Groups in a flat group are flat as well
+    fn write_nodes(&mut self, nodes: &[Node], flat: bool) {
+        for node in nodes {
+            match node {
+                Node::Token(token) => {
+                    self.write_token(token);
+
+                    if token.token.token_type() == LangTokenType::Semicolon {
+                        self.needs_newline = true; // End of a declaration
+                    }
+                },
+                Node::Group(group) if flat => self.write_flat_group(group),
+                Node::Group(group) => self.write_group(group),
+            }
+        }
+    }
+
+    fn write_group(&mut self, group: &Group) {
+        if can_be_flat(group) {
+            let mut flat = Printer { output: String::new(), line_start: false, ..*self };
+            flat.write_flat_group(group);
+
+            if group.items.is_empty() || self.column() + flat.output.len() <= MAX_WIDTH {
+                self.write_flat_group(group);
+                return;
+            }
+        }
+
+        self.write_token(&group.open);
+        self.indent += 1;
+
+        for (i, item) in group.items.iter().enumerate() {
+            self.newline();
+            self.write_nodes(&item.nodes, false);
+
+            if i < group.items.len() - 1 && !item.nodes.is_empty() {
+                self.write_comma();
+            }
+
+            for comment in &item.trailing_comments {
+                self.write_token(comment);
+            }
+        }
+
+        self.indent -= 1;
+        self.newline();
+        self.write(&group.close.token);
+    }
+
+    fn write_flat_group(&mut self, group: &Group) {
+        self.write_token(&group.open);
+
+        for (i, item) in group.items.iter().enumerate() {
+            self.write_nodes(&item.nodes, true);
+
+            if i < group.items.len() - 1 {
+                self.write_comma();
+            }
+
+            for comment in &item.trailing_comments {
+                self.write_token(comment);
+            }
+        }
+
+        self.write(&group.close.token);
+    }
+}
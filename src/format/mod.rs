@@ -1,15 +1,72 @@
-use std::fmt::{Display, Formatter, Write};
+use std::fmt::{Display, Formatter, LowerExp, Write};
 
 use crate::element::Element;
+use crate::parser::Span;
 use crate::util;
 
-pub enum Options {
+pub mod code;
+pub mod source;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
     Pretty {
         indentation: i32,
     },
+    Tabs,
     Compressed,
 }
 
+impl Layout {
+    // Names used for the `--format` command-line option
+    pub fn from_name(name: &str) -> Option<Layout> {
+        match name {
+            "pretty" => Some(Layout::Pretty { indentation: 2 }),
+            "tabs" => Some(Layout::Tabs),
+            "compressed" => Some(Layout::Compressed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloatPrecision {
+    Shortest, // Shortest representation that parses back to the same f64
+    Float32,  // Shortest representation of the nearest f32, like vanilla (0.19999999 instead of 0.19999998807907104)
+    Fixed(usize), // Rounded to at most this many decimal places, without trailing zeros
+}
+
+impl FloatPrecision {
+    // Names used for the `--floats` command-line option; a number means fixed precision
+    pub fn from_name(name: &str) -> Option<FloatPrecision> {
+        match name {
+            "shortest" => Some(FloatPrecision::Shortest),
+            "float32" => Some(FloatPrecision::Float32),
+            decimals => decimals.parse().ok().map(FloatPrecision::Fixed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub layout: Layout,
+    pub float_precision: FloatPrecision,
+    pub keep_decimal_point: bool, // Writes integral floats as `1.0` instead of `1`
+}
+
+impl Options {
+    pub fn new(layout: Layout) -> Options {
+        Options {
+            layout, float_precision: FloatPrecision::Shortest, keep_decimal_point: true,
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options::new(Layout::Pretty { indentation: 2 })
+    }
+}
+
 pub enum SymbolKind {
     Symbol, Function, Operator,
 }
@@ -25,8 +82,9 @@ impl Display for SymbolKind {
 }
 
 pub enum Error {
-    UnresolvedSymbol(SymbolKind, String, Element),
+    UnresolvedSymbol(SymbolKind, String, Span),
     NotSimpleElement(Element),
+    NonFiniteNumber(f64, Span),
     FormatError(std::fmt::Error),
 }
 
@@ -39,33 +97,72 @@ impl From<std::fmt::Error> for Error {
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::UnresolvedSymbol(kind, name, element) => write!(f, "Unresolved {} '{}': {:?}", kind, name, element),
+            Error::UnresolvedSymbol(kind, name, _) => write!(f, "Unresolved {} '{}'", kind, name),
             Error::NotSimpleElement(element) => write!(f, "Element is not simple (internal formatting failure): {:?}", element),
+            Error::NonFiniteNumber(value, _) => write!(f, "Number can't be represented in JSON: {}", value),
             Error::FormatError(err) => write!(f, "Formatting error: {}", err),
         }
     }
 }
 
+impl Error {
+    pub fn span(&self) -> Option<Span> {
+        let span = match self {
+            Error::UnresolvedSymbol(_, _, span) | Error::NonFiniteNumber(_, span) => *span,
+            Error::NotSimpleElement(element) => element.span(),
+            Error::FormatError(_) => return None,
+        };
+
+        Some(span).filter(|span| !span.is_none())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::UnresolvedSymbol(..) => "E0201",
+            Error::NotSimpleElement(_) => "E0202",
+            Error::NonFiniteNumber(..) => "E0203",
+            Error::FormatError(_) => "E0204",
+        }
+    }
+}
+
 type Result = std::result::Result<String, Error>;
 
 pub fn format_json(element: Element, options: Options) -> Result {
     let output = String::new();
 
-    match options {
-        Options::Pretty { indentation } => format_json_pretty(&element, output, &" ".repeat(indentation as usize), ""),
-        Options::Compressed => format_json_compressed(&element, output),
+    match options.layout {
+        Layout::Pretty { indentation } => format_json_pretty(&element, output, &options, &" ".repeat(indentation as usize), ""),
+        Layout::Tabs => format_json_pretty(&element, output, &options, "\t", ""),
+        Layout::Compressed => format_json_compressed(&element, output, &options),
+    }
+}
+
+// All unresolved symbols in an element, where formatting stops at the first one. Used to report
+// more errors in files with syntax errors, which are not formatted.
+pub fn unresolved_symbols(element: &Element) -> Vec<Error> {
+    match element {
+        Element::ArrayElement(values, _) => values.iter().flat_map(unresolved_symbols).collect(),
+        Element::ObjectElement(fields, _) => fields.iter()
+            .flat_map(|(key, value)| unresolved_symbols(key).into_iter().chain(unresolved_symbols(value)))
+            .collect(),
+        Element::ErrorElement(_) => Vec::new(),
+        _ => match format_json_compressed(element, String::new(), &Options::default()) {
+            Err(error @ Error::UnresolvedSymbol(..)) => vec![error],
+            _ => Vec::new(),
+        },
     }
 }
 
-fn format_json_pretty(element: &Element, mut output: String, indentation: &str, indent: &str) -> Result {
+fn format_json_pretty(element: &Element, mut output: String, options: &Options, indentation: &str, indent: &str) -> Result {
     match &element {
-        Element::ArrayElement(values) => {
+        Element::ArrayElement(values, _) => {
             let sub_indent = String::from(indent) + indentation;
             output.write_str("[\n")?;
 
             for (i, element) in values.iter().enumerate() {
                 output.write_str(&sub_indent)?;
-                output = format_json_pretty(element, output, indentation, &sub_indent)?;
+                output = format_json_pretty(element, output, options, indentation, &sub_indent)?;
 
                 if i < values.len() - 1 {
                     output.write_str(",\n")?;
@@ -78,16 +175,16 @@ fn format_json_pretty(element: &Element, mut output: String, indentation: &str,
             output.write_str("]")?;
             Ok(output)
         },
-        Element::ObjectElement(fields) => {
+        Element::ObjectElement(fields, _) => {
             let sub_indent = String::from(indent) + indentation;
             output.write_str("{\n")?;
 
             for (i, (key, value)) in fields.iter().enumerate() {
                 output.write_str(&sub_indent)?;
 
-                output = format_json_pretty(key, output, indentation, &sub_indent)?;
+                output = format_json_pretty(key, output, options, indentation, &sub_indent)?;
                 output.write_str(": ")?;
-                output = format_json_pretty(value, output, indentation, &sub_indent)?;
+                output = format_json_pretty(value, output, options, indentation, &sub_indent)?;
 
                 if i < fields.len() - 1 {
                     output.write_str(",\n")?;
@@ -100,54 +197,105 @@ fn format_json_pretty(element: &Element, mut output: String, indentation: &str,
             output.write_str("}")?;
             Ok(output)
         },
-        Element::BinaryElement { operator, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from(operator.text()), element.clone())),
-        Element::FunctionCallElement { name, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Function, name.clone(), element.clone())),
-        Element::NameElement(name) => Err(Error::UnresolvedSymbol(SymbolKind::Symbol, name.clone(), element.clone())),
-        _ => format_json_simple(element, output),
+        Element::UnaryElement { operator, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from(operator.text()), element.span())),
+        Element::BinaryElement { operator, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from(operator.text()), element.span())),
+        Element::FunctionCallElement { name, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Function, name.clone(), element.span())),
+        Element::ConditionalElement { .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from("if"), element.span())),
+        Element::SplineElement { .. } => Err(Error::UnresolvedSymbol(SymbolKind::Function, String::from("spline"), element.span())),
+        Element::NameElement(name, _) => Err(Error::UnresolvedSymbol(SymbolKind::Symbol, name.clone(), element.span())),
+        _ => format_json_simple(element, output, options),
     }
 }
 
-fn format_json_compressed(element: &Element, mut output: String) -> Result {
+fn format_json_compressed(element: &Element, mut output: String, options: &Options) -> Result {
     match &element {
-        Element::ArrayElement(values) => {
+        Element::ArrayElement(values, _) => {
             output.write_str("[")?;
 
-            for element in values {
-                output = format_json_compressed(element, output)?;
+            for (i, element) in values.iter().enumerate() {
+                if i > 0 {
+                    output.write_str(",")?;
+                }
+
+                output = format_json_compressed(element, output, options)?;
             }
 
             output.write_str("]")?;
             Ok(output)
         },
-        Element::ObjectElement(fields) => {
+        Element::ObjectElement(fields, _) => {
             output.write_str("{")?;
 
-            for (key, value) in fields {
-                output = format_json_compressed(key, output)?;
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    output.write_str(",")?;
+                }
+
+                output = format_json_compressed(key, output, options)?;
                 output.write_str(":")?;
-                output = format_json_compressed(value, output)?;
+                output = format_json_compressed(value, output, options)?;
             }
 
             output.write_str("}")?;
             Ok(output)
         },
-        Element::BinaryElement { operator, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from(operator.text()), element.clone())),
-        Element::FunctionCallElement { name, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Function, name.clone(), element.clone())),
-        Element::NameElement(name) => Err(Error::UnresolvedSymbol(SymbolKind::Symbol, name.clone(), element.clone())),
-        _ => format_json_simple(element, output),
+        Element::UnaryElement { operator, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from(operator.text()), element.span())),
+        Element::BinaryElement { operator, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from(operator.text()), element.span())),
+        Element::FunctionCallElement { name, .. } => Err(Error::UnresolvedSymbol(SymbolKind::Function, name.clone(), element.span())),
+        Element::ConditionalElement { .. } => Err(Error::UnresolvedSymbol(SymbolKind::Operator, String::from("if"), element.span())),
+        Element::SplineElement { .. } => Err(Error::UnresolvedSymbol(SymbolKind::Function, String::from("spline"), element.span())),
+        Element::NameElement(name, _) => Err(Error::UnresolvedSymbol(SymbolKind::Symbol, name.clone(), element.span())),
+        _ => format_json_simple(element, output, options),
     }
 }
 
-fn format_json_simple(element: &Element, mut output: String) -> Result {
+fn format_json_simple(element: &Element, mut output: String, options: &Options) -> Result {
     match element {
-        Element::NullElement => output.push_str("null"),
-        Element::BooleanElement(value) => output.push_str(if *value { "true" } else { "false" }),
-        Element::IntElement(value) => write!(output, "{}", value)?,
-        Element::FloatElement(value) => write!(output, "{}", value)?,
-        Element::StringElement(value) => write!(output, "\"{}\"", util::escape_str(&value))?,
-        Element::NameElement(name) => write!(output, "{}", name)?,
+        Element::NullElement(_) => output.push_str("null"),
+        Element::BooleanElement(value, _) => output.push_str(if *value { "true" } else { "false" }),
+        Element::IntElement(value, _) => write!(output, "{}", value)?,
+        Element::FloatElement(value, span) if !value.is_finite() => return Err(Error::NonFiniteNumber(*value, *span)),
+        Element::FloatElement(value, _) => output.push_str(&format_float(*value, options)),
+        Element::StringElement(value, _) => write!(output, "\"{}\"", util::escape_str(value))?,
+        Element::NameElement(name, _) => write!(output, "{}", name)?,
         _ => return Err(Error::NotSimpleElement(element.clone())),
     };
 
     Ok(output)
 }
+
+fn format_float(value: f64, options: &Options) -> String {
+    let output = match options.float_precision {
+        // Values outside the f32 range are written as f64, instead of as infinity
+        FloatPrecision::Float32 if (value as f32).is_finite() => format_shortest(value.abs(), value as f32),
+        FloatPrecision::Shortest | FloatPrecision::Float32 => format_shortest(value.abs(), value),
+        FloatPrecision::Fixed(decimals) => {
+            let output = format!("{:.*}", decimals, value);
+
+            if output.contains('.') {
+                output.trim_end_matches('0').trim_end_matches('.').to_owned()
+            } else {
+                output
+            }
+        },
+    };
+
+    if !options.keep_decimal_point || output.contains('.') {
+        return output;
+    }
+
+    // The decimal point goes before the exponent, like `1.0E10`
+    match output.find('E') {
+        Some(exponent) => format!("{}.0{}", &output[..exponent], &output[exponent..]),
+        None => output + ".0",
+    }
+}
+
+// Very small and large values use scientific notation, in the same cases as Java (and thus vanilla)
+fn format_shortest<T: Display + LowerExp>(magnitude: f64, value: T) -> String {
+    if magnitude != 0.0 && !(1e-3..1e7).contains(&magnitude) {
+        format!("{:e}", value).replace('e', "E")
+    } else {
+        format!("{}", value)
+    }
+}
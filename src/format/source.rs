@@ -0,0 +1,181 @@
+use std::fmt::Write;
+
+use crate::element::Element;
+use crate::format::{format_float, Options, Result};
+use crate::util;
+
+// Precedences like in the parser, used to decide where parentheses are needed
+const PRECEDENCE_ANY: u8 = 0;
+const PRECEDENCE_ARROW: u8 = 1;
+const PRECEDENCE_COMPARISON: u8 = 2;
+const PRECEDENCE_SUM: u8 = 3;
+const PRECEDENCE_FACTOR: u8 = 4;
+const PRECEDENCE_UNARY: u8 = 5;
+const PRECEDENCE_CALL: u8 = 6;
+
+const INDENTATION: &str = "  ";
+
+// Writes an element as source code of the language, for example after decompiling
+pub fn format_source(element: &Element) -> Result {
+    let mut output = String::new();
+    write_element(element, &mut output, "", PRECEDENCE_ANY)?;
+    output.push('\n');
+    Ok(output)
+}
+
+fn precedence(element: &Element) -> u8 {
+    match element {
+        Element::BinaryElement { operator, .. } => binary_precedence(operator.text()),
+        Element::UnaryElement { .. } => PRECEDENCE_UNARY,
+        Element::IntElement(value, _) if *value < 0 => PRECEDENCE_UNARY,
+        Element::FloatElement(value, _) if value.is_sign_negative() => PRECEDENCE_UNARY,
+        Element::ConditionalElement { .. } => PRECEDENCE_ANY, // The else branch would take everything after it
+        _ => PRECEDENCE_CALL,
+    }
+}
+
+fn binary_precedence(operator: &str) -> u8 {
+    match operator {
+        "->" => PRECEDENCE_ARROW,
+        "<" | "<=" | ">" | ">=" => PRECEDENCE_COMPARISON,
+        "+" | "-" => PRECEDENCE_SUM,
+        _ => PRECEDENCE_FACTOR,
+    }
+}
+
+// Adds parentheses if the element binds less strongly than `min_precedence`
+fn write_element(element: &Element, output: &mut String, indent: &str, min_precedence: u8) -> std::result::Result<(), std::fmt::Error> {
+    if precedence(element) < min_precedence {
+        output.push('(');
+        write_element(element, output, indent, PRECEDENCE_ANY)?;
+        output.push(')');
+        return Ok(());
+    }
+
+    let sub_indent = String::from(indent) + INDENTATION;
+
+    match element {
+        Element::NullElement(_) => output.push_str("null"),
+        Element::BooleanElement(value, _) => output.push_str(if *value { "true" } else { "false" }),
+        Element::IntElement(value, _) => write!(output, "{}", value)?,
+        // Non-finite numbers can't come from JSON, and aren't valid source code either
+        Element::FloatElement(value, _) => output.push_str(&format_float(*value, &Options::default())),
+        Element::StringElement(value, _) => write!(output, "\"{}\"", util::escape_str(value))?,
+        Element::NameElement(name, _) => output.push_str(name),
+        Element::ArrayElement(values, _) if values.iter().all(is_inline) => {
+            output.push('[');
+
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    output.push_str(", ");
+                }
+
+                write_element(value, output, indent, PRECEDENCE_ANY)?;
+            }
+
+            output.push(']');
+        },
+        Element::ArrayElement(values, _) => {
+            output.push_str("[\n");
+
+            for (i, value) in values.iter().enumerate() {
+                output.push_str(&sub_indent);
+                write_element(value, output, &sub_indent, PRECEDENCE_ANY)?;
+                output.push_str(if i < values.len() - 1 { ",\n" } else { "\n" });
+            }
+
+            output.push_str(indent);
+            output.push(']');
+        },
+        Element::ObjectElement(fields, _) if fields.is_empty() => output.push_str("{}"),
+        Element::ObjectElement(fields, _) => {
+            output.push_str("{\n");
+
+            for (i, (key, value)) in fields.iter().enumerate() {
+                output.push_str(&sub_indent);
+                write_element(key, output, &sub_indent, PRECEDENCE_ANY)?;
+                output.push_str(": ");
+                write_element(value, output, &sub_indent, PRECEDENCE_ANY)?;
+                output.push_str(if i < fields.len() - 1 { ",\n" } else { "\n" });
+            }
+
+            output.push_str(indent);
+            output.push('}');
+        },
+        Element::UnaryElement { operator, operand, .. } => {
+            output.push_str(operator.text());
+            write_element(operand, output, indent, PRECEDENCE_UNARY)?;
+        },
+        Element::BinaryElement { left, operator, right, .. } => {
+            // Operators are left-associative, so the right side needs parentheses at the same precedence
+            let precedence = binary_precedence(operator.text());
+            write_element(left, output, indent, precedence)?;
+            write!(output, " {} ", operator.text())?;
+            write_element(right, output, indent, precedence + 1)?;
+        },
+        Element::FunctionCallElement { receiver, name, arguments, .. } => {
+            if let Some(receiver) = receiver {
+                write_element(receiver, output, indent, PRECEDENCE_CALL)?;
+                output.push('.');
+            }
+
+            output.push_str(name);
+
+            if let Some(arguments) = arguments {
+                output.push('(');
+
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        output.push_str(", ");
+                    }
+
+                    write_element(argument, output, indent, PRECEDENCE_ANY)?;
+                }
+
+                output.push(')');
+            }
+        },
+        Element::NamedArgumentElement { name, value, .. } => {
+            write!(output, "{}: ", name)?;
+            write_element(value, output, indent, PRECEDENCE_ANY)?;
+        },
+        Element::ConditionalElement { condition, when_true, when_false, .. } => {
+            output.push_str("if ");
+            write_element(condition, output, indent, PRECEDENCE_ANY)?;
+            output.push_str(" then ");
+            write_element(when_true, output, indent, PRECEDENCE_ANY)?;
+            output.push_str(" else ");
+            write_element(when_false, output, indent, PRECEDENCE_ANY)?;
+        },
+        Element::SplineElement { coordinate, points, .. } => {
+            output.push_str("spline(");
+            write_element(coordinate, output, indent, PRECEDENCE_ANY)?;
+            output.push_str(") {\n");
+
+            for (i, point) in points.iter().enumerate() {
+                output.push_str(&sub_indent);
+                write_element(&point.location, output, &sub_indent, PRECEDENCE_ANY)?;
+                output.push_str(": ");
+                write_element(&point.value, output, &sub_indent, PRECEDENCE_ANY)?;
+
+                if let Some(derivative) = &point.derivative {
+                    output.push_str(" ' ");
+                    write_element(derivative, output, &sub_indent, PRECEDENCE_ANY)?;
+                }
+
+                output.push_str(if i < points.len() - 1 { ",\n" } else { "\n" });
+            }
+
+            output.push_str(indent);
+            output.push('}');
+        },
+        Element::ErrorElement(_) => return Err(std::fmt::Error), // Parts that failed to parse can't be written back
+    }
+
+    Ok(())
+}
+
+// Arrays of simple values are written on one line
+fn is_inline(element: &Element) -> bool {
+    !matches!(element, Element::ArrayElement(..) | Element::ObjectElement(..) | Element::SplineElement { .. })
+}
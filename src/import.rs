@@ -0,0 +1,193 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::module::Module;
+use crate::parser::{LangParser, SourceFiles, Span};
+use crate::parser::lexer::LangLexer;
+use crate::processor::ProcessError;
+
+// Loads the files imported by a module. Import paths are relative to the input directory,
+// or to one of the prelude directories. Every file in a prelude directory is imported implicitly.
+pub struct Importer {
+    roots: Vec<PathBuf>,
+    prelude: Vec<PathBuf>,
+}
+
+struct Loader<'a> {
+    importer: &'a Importer,
+    files: &'a mut SourceFiles,
+    stack: Vec<PathBuf>,
+    loaded: Vec<PathBuf>,
+    modules: Vec<Module>,
+    errors: Vec<ProcessError>,
+}
+
+impl Importer {
+    pub fn new(input: PathBuf) -> Importer {
+        Importer {
+            roots: vec![input],
+            prelude: Vec::new(),
+        }
+    }
+
+    pub fn add_prelude_directory(&mut self, directory: PathBuf) -> std::io::Result<()> {
+        let mut files = Vec::new();
+        collect_files(&directory, &mut files)?;
+        files.sort();
+
+        for file in files {
+            self.prelude.push(file.canonicalize()?);
+        }
+
+        self.roots.push(directory);
+        Ok(())
+    }
+
+    pub fn resolve_path(&self, path: &str) -> Option<PathBuf> {
+        for root in &self.roots {
+            let candidate = root.join(path);
+
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+
+            let candidate = candidate.with_extension("json");
+
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+
+        None
+    }
+
+    // Path used in error messages
+    pub fn display_path(&self, path: &Path) -> String {
+        for root in &self.roots {
+            if let Ok(root) = root.canonicalize() {
+                if let Ok(relative) = path.strip_prefix(&root) {
+                    return relative.to_string_lossy().into_owned();
+                }
+            }
+        }
+
+        path.to_string_lossy().into_owned()
+    }
+
+    // Loads every module imported by the module at `path`, directly or indirectly, in the order
+    // their declarations have to be processed. `path` itself is only used to detect cycles.
+    // The loaded files are added to `files`, so that errors in them can be located.
+    pub fn load_imports(&self, path: &Path, module: &Module, files: &mut SourceFiles) -> Result<Vec<Module>, Vec<ProcessError>> {
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+
+        let mut loader = Loader {
+            importer: self,
+            files,
+            stack: vec![path.clone()],
+            loaded: Vec::new(),
+            modules: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        if !self.prelude.contains(&path) {
+            for prelude_path in &self.prelude {
+                loader.load(prelude_path.clone());
+            }
+        }
+
+        for import in module.imports() {
+            loader.import(import);
+        }
+
+        if loader.errors.is_empty() {
+            Ok(loader.modules)
+        } else {
+            Err(loader.errors)
+        }
+    }
+}
+
+impl<'a> Loader<'a> {
+    fn chain(&self) -> Vec<String> {
+        self.stack.iter().map(|path| self.importer.display_path(path)).collect()
+    }
+
+    fn import(&mut self, import: &str) {
+        match self.importer.resolve_path(import).and_then(|path| path.canonicalize().ok()) {
+            Some(path) => self.load(path),
+            None => {
+                let chain = self.chain();
+
+                self.errors.push(ProcessError::ImportNotFound {
+                    path: import.to_owned(),
+                    chain,
+                });
+            },
+        }
+    }
+
+    fn load(&mut self, path: PathBuf) {
+        if self.stack.contains(&path) {
+            let mut chain = self.chain();
+            chain.push(self.importer.display_path(&path));
+
+            self.errors.push(ProcessError::ImportCycle(chain));
+            return;
+        }
+
+        if self.loaded.contains(&path) {
+            return;
+        }
+
+        let module = match read_module(&path, self.files) {
+            Ok(module) => module,
+            Err((message, span)) => {
+                self.errors.push(ProcessError::ImportFailed {
+                    path: self.importer.display_path(&path),
+                    message,
+                    span,
+                });
+                return;
+            }
+        };
+
+        self.stack.push(path.clone());
+
+        for import in module.imports() {
+            self.import(import);
+        }
+
+        self.stack.pop();
+        self.loaded.push(path);
+        self.modules.push(module);
+    }
+}
+
+// Errors are returned with their span, if they have one
+fn read_module(path: &Path, files: &mut SourceFiles) -> Result<Module, (String, Span)> {
+    let mut source = String::new();
+    File::open(path).and_then(|mut file| file.read_to_string(&mut source))
+        .map_err(|err| (format!("IO error: {}", err), Span::none()))?;
+
+    let file = files.add(path.to_path_buf(), source);
+    let lexer = LangLexer::new(files.source(file).expect("file was just added"));
+    let mut parser = LangParser::new_with_file(lexer, file);
+
+    parser.parse_module().map_err(|err| match err.span(file) {
+        Some(span) => (err.message(), span),
+        None => (err.to_string(), Span::none()),
+    })
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
@@ -1,14 +1,19 @@
 use std::fmt::{Display, Formatter};
 use pathdiff;
 
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
 
 pub enum Error {
     Io(std::io::Error),
     UnknownFile(PathBuf),
     PathRelativizeFailure,
+    Zip(crate::zip::Error),
+    DuplicateEntry(String, PathBuf, PathBuf), // Two files with the same path in a zip
 }
 
 impl From<std::io::Error> for Error {
@@ -17,95 +22,269 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<crate::zip::Error> for Error {
+    fn from(error: crate::zip::Error) -> Self {
+        Error::Zip(error)
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::Io(err) => write!(f, "IO error: {}", err),
             Error::UnknownFile(path) => write!(f, "Unknown file (path is neither a file nor a directory): {}", path.to_string_lossy()),
             Error::PathRelativizeFailure => write!(f, "Path relativize failure!"),
+            Error::Zip(err) => write!(f, "Zip error: {}", err),
+            Error::DuplicateEntry(name, first, second) => write!(f, "Both {} and {} would be written to {}",
+                first.to_string_lossy(), second.to_string_lossy(), name),
         }
     }
 }
 
-pub fn process(input: PathBuf, output: PathBuf, f: &mut impl FnMut(String) -> Option<String>) {
-    let original_input = input.clone();
+pub enum FileResult {
+    Output(String),
+    NoOutput, // For files that only contain definitions for other files
+    Failed,
+}
 
-    match process_path(original_input.as_path(), input, output, f) {
-        Ok(success) => if success {
-            println!();
-            println!("Done.");
-        } else {
-            println!();
-            println!("Done with errors.");
+// Processes a file, or all files of a directory in the order of their paths, followed by a
+// summary of the files that failed
+pub fn process(input: PathBuf, output: PathBuf, f: &mut impl FnMut(&Path, String) -> FileResult) {
+    if !input.is_dir() {
+        match process_file(&input, input.clone(), output, f) {
+            Ok(success) => if success {
+                println!();
+                println!("Done.");
+            } else {
+                println!();
+                println!("Done with errors.");
+            },
+            Err(err) => println!("{}", err),
+        }
+
+        return;
+    }
+
+    let files = match list_files(&input) {
+        Ok(files) => files,
+        Err(err) => {
+            println!("{}", err);
+            return;
         },
-        Err(err) => println!("{}", err),
+    };
+
+    let mut failed = Vec::new();
+    let mut blank_line = false;
+
+    for file in &files {
+        if blank_line {
+            println!();
+        }
+
+        let output = output.join(file);
+        let result = output.parent().map_or(Ok(()), std::fs::create_dir_all).map_err(Error::from)
+            .and_then(|_| process_file(&input, input.join(file), output, f));
+
+        let success = match result {
+            Ok(success) => success,
+            Err(err) => {
+                println!("Errors:\n- {}", err);
+                false
+            },
+        };
+
+        blank_line = !success;
+
+        if !success {
+            failed.push(file);
+        }
     }
+
+    println!();
+    write_summary(&mut std::io::stdout(), &failed, files.len());
 }
 
-pub fn process_path(original_input: &Path, input: PathBuf, output: PathBuf, f: &mut impl FnMut(String) -> Option<String>) -> Result<bool, Error> {
-    let metadata = input.metadata()?;
+// Processes a single file, `original_input` is the path that file names are shown relative to
+pub fn process_file(original_input: &Path, input: PathBuf, output: PathBuf, f: &mut impl FnMut(&Path, String) -> FileResult) -> Result<bool, Error> {
+    if !input.is_file() {
+        return Err(Error::UnknownFile(input));
+    }
+
+    let relative_path_for_display = relativize(original_input, &input)?;
+    println!("Processing {}", relative_path_for_display.to_string_lossy());
+
+    let mut input_file = File::open(&input)?;
+
+    let mut input_str = String::new();
+    input_file.read_to_string(&mut input_str)?;
+
+    let output_str = match f(&input, input_str) {
+        FileResult::Output(result) => result,
+        FileResult::NoOutput => return Ok(true),
+        FileResult::Failed => return Ok(false),
+    };
 
-    if metadata.is_dir() {
-        std::fs::create_dir_all(&output)?;
-        let mut error = false;
+    drop(input_file);
+    let mut output_file = File::create(output)?;
+    output_file.write_all(output_str.as_bytes())?;
+    Ok(true)
+}
+
+// Like `process`, but processes the files of a directory on `threads` threads. The closure
+// returns the messages for a file instead of printing them; they are printed in the order of
+// the file paths, followed by a summary of the files that failed.
+pub fn process_parallel<F>(input: PathBuf, output: PathBuf, threads: usize, f: &F)
+    where F: Fn(&Path, String) -> (FileResult, String) + Sync {
+    if !input.is_dir() {
+        return process(input, output, &mut |path, input_str| {
+            let (result, messages) = f(path, input_str);
+            print!("{}", messages);
+            result
+        });
+    }
+
+    let files = match list_files(&input) {
+        Ok(files) => files,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        },
+    };
+
+    let mut stdout = std::io::stdout();
+    let failed = process_files(&input, &files, threads, f, |file, output_str| {
+        let output = output.join(file);
+
+        if let Some(directory) = output.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        std::fs::write(output, output_str)?;
+        Ok(())
+    }, &mut stdout);
+
+    println!();
+    write_summary(&mut stdout, &failed, files.len());
+}
+
+// Processes files (relative to the input directory) on `threads` threads. The output of each
+// file is passed to `write` on the calling thread, in the order of the files, and the messages
+// are written to `out` in the same order. Returns the files that failed.
+pub fn process_files<'a, F, W, O>(input: &Path, files: &'a [PathBuf], threads: usize, f: &F, mut write: W, out: &mut O) -> Vec<&'a PathBuf>
+    where F: Fn(&Path, String) -> (FileResult, String) + Sync, W: FnMut(&Path, String) -> Result<(), Error>, O: Write {
+    let next_file = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut failed = Vec::new();
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, files.len().max(1)) {
+            let sender = sender.clone();
+            let next_file = &next_file;
+
+            scope.spawn(move || {
+                loop {
+                    let i = next_file.fetch_add(1, Ordering::Relaxed);
+
+                    let Some(file) = files.get(i) else {
+                        return;
+                    };
+
+                    let path = input.join(file);
+                    let result = std::fs::read_to_string(&path).map(|input_str| f(&path, input_str));
+
+                    if sender.send((i, result)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        drop(sender); // The loop below ends when all threads are done
+
+        // Files that finished before the ones before them are held back
+        let mut finished = BTreeMap::new();
+        let mut next_message = 0;
         let mut blank_line = false;
 
-        for file_entry in std::fs::read_dir(&input)? {
-            match file_entry {
-                Ok(file_entry) => {
-                    let path = file_entry.path();
-                    let relative_path = relativize(&input, &path)?;
+        for (i, result) in receiver {
+            finished.insert(i, result);
+
+            while let Some(result) = finished.remove(&next_message) {
+                let file = &files[next_message];
+                let mut report = String::new();
+
+                if blank_line {
+                    report.push('\n');
+                }
+
+                report.push_str(&format!("Processing {}\n", file.to_string_lossy()));
 
-                    if blank_line {
-                        println!();
-                        blank_line = false;
+                let success = match result.map_err(Error::from).and_then(|(result, messages)| {
+                    report.push_str(&messages);
+
+                    match result {
+                        FileResult::Output(output_str) => write(file, output_str).map(|_| true),
+                        FileResult::NoOutput => Ok(true),
+                        FileResult::Failed => Ok(false),
                     }
+                }) {
+                    Ok(success) => success,
+                    Err(err) => {
+                        report.push_str(&format!("Errors:\n- {}\n", err));
+                        false
+                    },
+                };
 
-                    match process_path(original_input, path, output.join(relative_path), f) {
-                        Ok(success) => if !success {
-                            error = true;
-                            blank_line = true;
-                        },
-                        Err(err) => {
-                            println!("Errors:\n- {}", err);
-                            blank_line = true;
-                        }
-                    };
-                },
-                Err(err) => {
-                    println!("IO error while iterating through directory: {}", err); // Skip this entry
-                },
+                let _ = out.write_all(report.as_bytes()); // Files are still written if the messages can't be
+                blank_line = !success;
+
+                if !success {
+                    failed.push(file);
+                }
+
+                next_message += 1;
             }
         }
+    });
 
-        if error {
-            Ok(false)
-        } else {
-            Ok(true)
-        }
-    } else if metadata.is_file() {
-        let relative_path_for_display = relativize(original_input, &input)?;
-        println!("Processing {}", relative_path_for_display.to_string_lossy());
+    failed
+}
+
+pub fn write_summary(out: &mut impl Write, failed: &[&PathBuf], total: usize) {
+    let mut summary = if failed.is_empty() {
+        String::from("Done.\n")
+    } else {
+        format!("Done with errors, {} of {} files failed:\n", failed.len(), total)
+    };
 
-        let mut input_file = File::open(input)?;
+    for file in failed {
+        summary.push_str(&format!("- {}\n", file.to_string_lossy()));
+    }
 
-        let mut input_str = String::new();
-        input_file.read_to_string(&mut input_str)?;
+    let _ = out.write_all(summary.as_bytes());
+}
 
-        let output_str = match f(input_str) {
-            Some(result) => result,
-            None => return Ok(false),
-        };
+// All files in a directory and its subdirectories, relative to it and sorted
+pub fn list_files(directory: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
 
-        drop(input_file);
-        let mut output_file = File::create(output)?;
-        output_file.write_all(output_str.as_bytes())?;
-        Ok(true)
-    } else {
-        Err(Error::UnknownFile(input))
+    while let Some(current) = directories.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                directories.push(path);
+            } else {
+                files.push(relativize(directory, &path)?);
+            }
+        }
     }
+
+    files.sort();
+    Ok(files)
 }
 
 fn relativize(base: &Path, path: &Path) -> Result<PathBuf, Error> {
-    pathdiff::diff_paths(path, base).ok_or_else(|| Error::PathRelativizeFailure)
+    pathdiff::diff_paths(path, base).ok_or(Error::PathRelativizeFailure)
 }
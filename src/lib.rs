@@ -2,10 +2,20 @@
 // #![feature(box_patterns)]
 
 pub mod element;
+pub mod module;
 pub mod parser;
 pub mod util;
 pub mod processor;
 pub mod format;
 pub mod io;
+pub mod import;
 
 pub mod process;
+pub mod decompile;
+pub mod diagnostic;
+pub mod builtins;
+pub mod compile;
+pub mod diff;
+pub mod watch;
+pub mod zip;
+pub mod pack;
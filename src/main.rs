@@ -1,10 +1,26 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
+use worldgen_lang::import::Importer;
+use worldgen_lang::io::FileResult;
+use worldgen_lang::{builtins, compile, decompile, diagnostic, diff, format, io, pack};
 use worldgen_lang::element::Element;
-use worldgen_lang::{format, io, string_element, object_element, util};
-use worldgen_lang::parser::LangParser;
+use worldgen_lang::diagnostic::Diagnostic;
+use worldgen_lang::parser::{LangParser, SourceFiles};
 use worldgen_lang::parser::lexer::LangLexer;
-use worldgen_lang::processor::{BinaryOperator, CustomThreeArgsFunction, ElementProcessor, NoArgFunction, OneArgFunction, ProcessResult, ProcessWarning, TwoArgsFunction};
+use worldgen_lang::processor::{registry, ElementProcessor};
+use worldgen_lang::watch::{Dependencies, FileWatcher};
+
+// How often --watch checks for changed files
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(PartialEq, Eq)]
+enum Command {
+    Compile,
+    Decompile, // JSON to source files
+    Format, // Source files to formatted source files
+    Diff, // Compares JSON files
+}
 
 fn main() {
     /* let source = r#"
@@ -15,116 +31,583 @@ fn main() {
 
     let _source = r#"5"#; */
 
-    let args: Vec<String> = std::env::args().skip(1).collect(); // Skip the executable path
+    let mut args = std::env::args().skip(1).peekable(); // Skip the executable path
 
-    if args.len() != 2 {
-        eprintln!("There must be exactly two command-line arguments (input and output)!");
+    let command = match args.peek().map(String::as_str) {
+        Some("decompile") => {
+            args.next();
+            Command::Decompile
+        },
+        Some("fmt") => {
+            args.next();
+            Command::Format
+        },
+        Some("diff") => {
+            args.next();
+            Command::Diff
+        },
+        _ => Command::Compile,
+    };
+
+    let mut paths = Vec::new();
+    let mut prelude_directories = Vec::new();
+    let mut registry_files = Vec::new();
+    let mut format_options = format::Options::default();
+    let mut tolerance = 0.0;
+    let mut watch = false;
+    let mut threads = 1;
+    let mut pack_options = pack::PackOptions::new();
+    let mut has_pack_options = false;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--prelude" => match args.next() {
+                Some(directory) => prelude_directories.push(PathBuf::from(directory)),
+                None => {
+                    eprintln!("--prelude needs a directory as argument!");
+                    return;
+                },
+            },
+            "--registry" => match args.next() {
+                Some(file) => registry_files.push(PathBuf::from(file)),
+                None => {
+                    eprintln!("--registry needs a file as argument!");
+                    return;
+                },
+            },
+            "--format" => match args.next().as_deref().and_then(format::Layout::from_name) {
+                Some(layout) => format_options.layout = layout,
+                None => {
+                    eprintln!("--format needs one of pretty, tabs or compressed as argument!");
+                    return;
+                },
+            },
+            "--floats" => match args.next().as_deref().and_then(format::FloatPrecision::from_name) {
+                Some(precision) => format_options.float_precision = precision,
+                None => {
+                    eprintln!("--floats needs one of shortest, float32 or a number of decimal places as argument!");
+                    return;
+                },
+            },
+            "--no-decimal-point" => format_options.keep_decimal_point = false,
+            "--watch" => watch = true,
+            "-j" => match args.next().and_then(|value| value.parse::<usize>().ok()) {
+                Some(value) if value > 0 => threads = value,
+                _ => {
+                    eprintln!("-j needs a number of threads as argument!");
+                    return;
+                },
+            },
+            "--pack-format" => match args.next().and_then(|value| value.parse::<i32>().ok()) {
+                Some(value) if value > 0 => {
+                    pack_options.pack_format = value;
+                    has_pack_options = true;
+                },
+                _ => {
+                    eprintln!("--pack-format needs a positive number as argument!");
+                    return;
+                },
+            },
+            "--description" => match args.next() {
+                Some(description) => {
+                    pack_options.description = description;
+                    has_pack_options = true;
+                },
+                None => {
+                    eprintln!("--description needs a text as argument!");
+                    return;
+                },
+            },
+            "--namespace" => match args.next() {
+                Some(namespace) if pack::is_namespace(&namespace) => {
+                    pack_options.namespace = namespace;
+                    has_pack_options = true;
+                },
+                _ => {
+                    eprintln!("--namespace needs a namespace (lowercase letters, digits, _, - and .) as argument!");
+                    return;
+                },
+            },
+            "--tolerance" => match args.next().and_then(|value| value.parse::<f64>().ok()) {
+                Some(value) if value >= 0.0 => tolerance = value,
+                _ => {
+                    eprintln!("--tolerance needs a positive number as argument!");
+                    return;
+                },
+            },
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    if paths.len() != 2 {
+        eprintln!("There must be exactly two paths as command-line arguments (input and output)!");
         return;
     }
 
-    let input = PathBuf::from(&args[0]);
-    let output = PathBuf::from(&args[1]);
+    let output = paths.pop().unwrap();
+    let input = paths.pop().unwrap();
 
-    let mut processor = ElementProcessor::new();
-    // processor.add_postprocessor(process::process_operators);
-    // processor.add_postprocessor(process::process_functions);
-
-    processor.add_no_arg_function(NoArgFunction::new(String::from("blend_alpha")));
-    processor.add_no_arg_function(NoArgFunction::new(String::from("blend_offset")));
-    processor.add_no_arg_function(NoArgFunction::new(String::from("beardifier")));
-    processor.add_no_arg_function(NoArgFunction::new(String::from("old_blended_noise")));
-    processor.add_no_arg_function(NoArgFunction::new(String::from("end_islands")));
-
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("abs")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("half_negative")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("quarter_negative")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("square")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("cube")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("squeeze")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("interpolated")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("flat_cache")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("cache_2d")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("cache_once")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("cache_all_in_cell")));
-    processor.add_one_arg_function(OneArgFunction::new_with_method_syntax(String::from("slide")));
-
-    processor.add_two_args_function(TwoArgsFunction::new_without_method_syntax(String::from("min")));
-    processor.add_two_args_function(TwoArgsFunction::new_without_method_syntax(String::from("max")));
-
-    processor.add_custom_three_args_function(CustomThreeArgsFunction::new(String::from("lerp"),
-        Box::new(|a, b, c| ProcessResult::new(object_element!(
-            string_element!("type") => string_element!("minecraft:add"),
-            string_element!("argument1") => object_element!(
-                string_element!("type") => string_element!("minecraft:mul"),
-                string_element!("argument1") => b,
-                string_element!("argument2") => object_element!(
-                    string_element!("type") => string_element!("minecraft:add"),
-                    string_element!("argument1") => Element::FloatElement(1.0),
-                    string_element!("argument2") => object_element!(
-                        string_element!("type") => string_element!("minecraft:mul"),
-                        string_element!("argument1") => object_element!(
-                            string_element!("type") => string_element!("minecraft:cache_once"),
-                            string_element!("argument") => a.clone()
-                        ),
-                        string_element!("argument2") => Element::FloatElement(-1.0)
-                    )
-                )
-            ),
-            string_element!("argument2") => object_element!(
-                string_element!("type") => string_element!("minecraft:mul"),
-                string_element!("argument1") => c,
-                string_element!("argument2") => object_element!(
-                    string_element!("type") => string_element!("minecraft:cache_once"),
-                    string_element!("argument") => a.clone()
-                )
-            )
-    ), vec![ ProcessWarning::LerpDuplicatedCode(Element::FunctionCallElement {
-            receiver: Some(Box::new(a)), name: String::from("cache_once"), arguments: Some(Vec::new()) }) ], Vec::new()))));
-
-    processor.add_binary_operator(BinaryOperator::new(String::from("+"), String::from("add")));
-    processor.add_binary_operator(BinaryOperator::new(String::from("*"), String::from("mul")));
-
-    io::process(input, output, &mut |input_str| {
-        let lexer = LangLexer::new(&input_str); // Moved into parser
-        let mut parser = LangParser::new(lexer);
+    if (watch || threads > 1) && command != Command::Compile {
+        eprintln!("--watch and -j can only be used when compiling!");
+        return;
+    }
 
-        let element = match parser.parse_full() {
-            Ok(element) => element,
+    if watch && threads > 1 {
+        eprintln!("--watch can't be used together with -j!");
+        return;
+    }
+
+    // Compiling to a .zip output writes a data pack
+    let zip = command == Command::Compile && output.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+
+    if has_pack_options && !zip {
+        eprintln!("--pack-format, --description and --namespace can only be used when compiling to a .zip file!");
+        return;
+    }
+
+    if zip && watch {
+        eprintln!("--watch can't be used when compiling to a .zip file!");
+        return;
+    }
+
+    if command == Command::Diff {
+        if !diff(&input, &output, tolerance) {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if command == Command::Format {
+        format_sources(input, output);
+        return;
+    }
+
+    let importer = match create_importer(&input, &prelude_directories) {
+        Ok(importer) => importer,
+        Err(err) => {
+            eprintln!("Could not read prelude directory: {}", err);
+            return;
+        },
+    };
+
+    let processor = match create_processor(&registry_files) {
+        Some(processor) => processor,
+        None => return,
+    };
+
+    if command == Command::Decompile {
+        decompile(&processor, input, output);
+        return;
+    }
+
+    if watch {
+        watch_input(input, output, prelude_directories, &processor, format_options);
+        return;
+    }
+
+    if zip {
+        pack::process_to_zip(input, output, threads, &pack_options, &|path, input_str| {
+            let (compilation, messages) = compile_file(path, input_str, &importer, &processor, format_options);
+            (compilation.result, messages)
+        });
+        return;
+    }
+
+    if threads > 1 {
+        io::process_parallel(input, output, threads, &|path, input_str| {
+            let (compilation, messages) = compile_file(path, input_str, &importer, &processor, format_options);
+            (compilation.result, messages)
+        });
+        return;
+    }
+
+    io::process(input, output, &mut |path, input_str| {
+        let (compilation, messages) = compile_file(path, input_str, &importer, &processor, format_options);
+        print!("{}", messages);
+        compilation.result
+    });
+}
+
+fn create_importer(input: &Path, prelude_directories: &[PathBuf]) -> std::io::Result<Importer> {
+    let mut importer = Importer::new(input.to_path_buf());
+
+    for directory in prelude_directories {
+        importer.add_prelude_directory(directory.clone())?;
+    }
+
+    Ok(importer)
+}
+
+// Compiles a file, returning its diagnostics as the messages to print
+fn compile_file(path: &Path, input_str: String, importer: &Importer, processor: &ElementProcessor<'_>,
+                format_options: format::Options) -> (compile::Compilation, String) {
+    let compilation = compile::compile_file(path, input_str, importer, processor, format_options);
+    let color = diagnostic::use_color(&std::io::stdout());
+    let mut messages = String::new();
+
+    for diagnostic in &compilation.diagnostics {
+        messages.push('\n');
+        messages.push_str(&diagnostic.render(&compilation.files, color));
+    }
+
+    if matches!(compilation.result, FileResult::Failed) && compilation.diagnostics.is_empty() {
+        messages.push_str("- Error during processing.\n");
+    }
+
+    (compilation, messages)
+}
+
+struct Watch<'a> {
+    input: PathBuf, // Canonical, like the paths from the watcher
+    output: PathBuf,
+    prelude_directories: Vec<PathBuf>,
+    processor: &'a ElementProcessor<'a>,
+    format_options: format::Options,
+    dependencies: Dependencies,
+    failed: Vec<PathBuf>,
+}
+
+impl Watch<'_> {
+    // Compiles the files, returning how many of them failed
+    fn rebuild(&mut self, files: &[PathBuf]) -> usize {
+        // Created again for every rebuild, because files can be added to the prelude directories
+        let importer = match create_importer(&self.input, &self.prelude_directories) {
+            Ok(importer) => importer,
             Err(err) => {
-                println!("Errors:\n- {}", err);
-                return None;
+                println!("Could not read prelude directory: {}", err);
+                return files.len();
             },
         };
 
-        let mut result = processor.process(element);
+        let mut failures = 0;
+
+        for file in files {
+            let output = self.output.join(file.strip_prefix(&self.input).expect("file is in the input directory"));
+            let mut dependencies = Vec::new();
+
+            let result = output.parent().map_or(Ok(()), std::fs::create_dir_all).map_err(io::Error::from)
+                .and_then(|_| io::process_file(&self.input, file.clone(), output, &mut |path, input_str| {
+                    let (compilation, messages) = compile_file(path, input_str, &importer, self.processor, self.format_options);
+                    print!("{}", messages);
+                    dependencies = compilation.files.paths().map(|path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf())).collect();
+                    compilation.result
+                }));
+
+            self.dependencies.set(file.clone(), dependencies);
+            self.failed.retain(|failed| failed != file);
 
-        if !result.errors.is_empty() {
-            println!("Errors:");
+            if !matches!(result, Ok(true)) {
+                if let Err(err) = result {
+                    println!("Errors:\n- {}", err);
+                }
 
-            for error in util::distinct(result.errors_mut()) {
-                println!("- {}", error);
+                failures += 1;
+                self.failed.push(file.clone());
             }
+        }
 
-            return None;
-        } else if !result.warnings.is_empty() {
-            println!("Warnings:");
+        failures
+    }
 
-            for error in util::distinct(result.warnings_mut()) {
-                println!("- {}", error);
+    // Removes the outputs of removed input files, returning how many were removed
+    fn remove(&mut self, files: &[PathBuf]) -> usize {
+        let mut removed = 0;
+
+        for file in files {
+            self.dependencies.remove(file);
+            self.failed.retain(|failed| failed != file);
+
+            if let Ok(relative_path) = file.strip_prefix(&self.input) {
+                if std::fs::remove_file(self.output.join(relative_path)).is_ok() {
+                    println!("Removed {}", relative_path.to_string_lossy());
+                    removed += 1;
+                }
             }
-        } else if result.element.is_none() {
-            println!("- Error during processing.");
+        }
+
+        removed
+    }
+}
+
+// Compiles the input folder, then keeps recompiling changed files and the files that import
+// them until the program is stopped
+fn watch_input(input: PathBuf, output: PathBuf, prelude_directories: Vec<PathBuf>, processor: &ElementProcessor<'_>,
+               format_options: format::Options) {
+    let input = match input.canonicalize() {
+        Ok(input) if input.is_dir() => input,
+        _ => {
+            eprintln!("--watch needs an input folder!");
+            return;
+        },
+    };
+
+    let mut watcher = FileWatcher::new(std::iter::once(input.clone()).chain(prelude_directories.iter().cloned()).collect());
+    let mut watch = Watch {
+        input, output, prelude_directories, processor, format_options,
+        dependencies: Dependencies::new(),
+        failed: Vec::new(),
+    };
+
+    let start = Instant::now();
+    let mut files: Vec<PathBuf> = watcher.files().filter(|file| file.starts_with(&watch.input)).map(Path::to_path_buf).collect();
+    files.sort();
+
+    let failures = watch.rebuild(&files);
+    print_watch_status(files.len(), failures, 0, start);
+
+    loop {
+        std::thread::sleep(WATCH_INTERVAL);
+
+        let changes = watcher.poll();
+
+        if changes.is_empty() {
+            continue;
+        }
+
+        let start = Instant::now();
+        let removed = watch.remove(&changes.removed);
+
+        let modified: Vec<PathBuf> = changes.added.iter().chain(&changes.changed).chain(&changes.removed).cloned().collect();
+        let mut files: Vec<PathBuf> = changes.added.iter().chain(&changes.changed)
+            .filter(|file| file.starts_with(&watch.input))
+            .cloned()
+            .collect();
+        files.extend(watch.dependencies.dependents(&modified));
+
+        if !changes.added.is_empty() { // Imports that were missing might exist now
+            files.extend(watch.failed.iter().cloned());
+        }
+
+        files.retain(|file| file.is_file());
+        files.sort();
+        files.dedup();
+
+        let failures = watch.rebuild(&files);
+        print_watch_status(files.len(), failures, removed, start);
+    }
+}
+
+fn print_watch_status(built: usize, failures: usize, removed: usize, start: Instant) {
+    let mut status = format!("Built {} file{}", built, if built == 1 { "" } else { "s" });
+
+    if failures > 0 {
+        status += &format!(", {} failed", failures);
+    }
+
+    if removed > 0 {
+        status += &format!(", removed {} output{}", removed, if removed == 1 { "" } else { "s" });
+    }
+
+    println!();
+    println!("{} in {} ms. Watching for changes...", status, start.elapsed().as_millis());
+}
+
+fn print_diagnostic(diagnostic: &Diagnostic, files: &SourceFiles) {
+    println!();
+    print!("{}", diagnostic.render(files, diagnostic::use_color(&std::io::stdout())));
+}
+
+fn create_processor(registry_files: &[PathBuf]) -> Option<ElementProcessor<'static>> {
+    let mut processor = ElementProcessor::new();
+
+    // Functions from registry files are added first, so that they take precedence over the built-in ones
+    for file in registry_files {
+        if !load_registry_file(&mut processor, file) {
             return None;
         }
+    }
+
+    Some(builtins::with_builtins(processor))
+}
+
+// Turns density function JSON files into source files
+fn decompile(processor: &ElementProcessor<'_>, input: PathBuf, output: PathBuf) {
+    io::process(input, output, &mut |path, input_str| {
+        let mut files = SourceFiles::new();
+        let file = files.add(path.to_path_buf(), input_str);
+
+        let lexer = LangLexer::new(files.source(file).expect("file was just added"));
+        let mut parser = LangParser::new_with_file(lexer, file);
+
+        let element = match parser.parse_full() {
+            Ok(element) => element,
+            Err(err) => {
+                print_diagnostic(&Diagnostic::from_parse_error(&err, file), &files);
+                return FileResult::Failed;
+            },
+        };
+
+        match format::source::format_source(&decompile::decompile(processor, element)) {
+            Ok(source) => FileResult::Output(source),
+            Err(err) => {
+                println!("Errors:\n- {}", err);
+                FileResult::Failed
+            },
+        }
+    });
+}
 
-        let formatted_result = match format::format_json(result.element.unwrap(), format::Options::Pretty { indentation: 2 }) {
-            Ok(result) => result,
+// Re-emits source files with consistent formatting
+fn format_sources(input: PathBuf, output: PathBuf) {
+    io::process(input, output, &mut |path, input_str| {
+        let module = match LangParser::new(LangLexer::new(&input_str)).parse_module() {
+            Ok(module) => module,
+            Err(err) => {
+                let mut files = SourceFiles::new();
+                let file = files.add(path.to_path_buf(), input_str);
+                print_diagnostic(&Diagnostic::from_parse_error(&err, file), &files);
+                return FileResult::Failed;
+            },
+        };
+
+        let formatted = match format::code::format_code(&input_str) {
+            Ok(formatted) => formatted,
             Err(err) => {
                 println!("Errors:\n- {}", err);
-                return None;
-            }
+                return FileResult::Failed;
+            },
         };
 
-        Some(formatted_result)
+        // Formatting must never change what the file means
+        let formatted_module = LangParser::new(LangLexer::new(&formatted)).parse_module();
+
+        if formatted_module.map(|formatted_module| format!("{:?}", formatted_module)).ok() != Some(format!("{:?}", module)) {
+            println!("Errors:\n- Formatting changed the meaning of the file, leaving it unformatted");
+            return FileResult::Failed;
+        }
+
+        FileResult::Output(formatted)
     });
 }
+
+// Compares two JSON files, or the files with the same paths in two directories. Returns whether
+// they are the same.
+fn diff(left: &Path, right: &Path, tolerance: f64) -> bool {
+    if left.is_file() && right.is_file() {
+        return diff_files(left, right, None, tolerance);
+    }
+
+    let (left_files, right_files) = match (io::list_files(left), io::list_files(right)) {
+        (Ok(left_files), Ok(right_files)) => (left_files, right_files),
+        (Err(err), _) | (_, Err(err)) => {
+            println!("Could not read directories (both paths must be files or directories): {}", err);
+            return false;
+        },
+    };
+
+    let mut paths: Vec<&PathBuf> = left_files.iter().chain(&right_files).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut different_files = 0;
+
+    for path in &paths {
+        let same = match (left_files.contains(path), right_files.contains(path)) {
+            (true, true) => diff_files(&left.join(path), &right.join(path), Some(path), tolerance),
+            (in_left, _) => {
+                println!("Only in {}: {}", if in_left { left } else { right }.to_string_lossy(), path.to_string_lossy());
+                false
+            },
+        };
+
+        if !same {
+            different_files += 1;
+        }
+    }
+
+    println!();
+
+    if different_files == 0 {
+        println!("No differences in {} files.", paths.len());
+    } else {
+        println!("{} of {} files differ.", different_files, paths.len());
+    }
+
+    different_files == 0
+}
+
+fn diff_files(left: &Path, right: &Path, name: Option<&Path>, tolerance: f64) -> bool {
+    let (left_element, right_element) = match (read_json(left), read_json(right)) {
+        (Some(left_element), Some(right_element)) => (left_element, right_element),
+        _ => return false,
+    };
+
+    let differences = diff::diff(&left_element, &right_element, tolerance);
+
+    if differences.is_empty() {
+        if name.is_none() {
+            println!("No differences.");
+        }
+
+        return true;
+    }
+
+    if let Some(name) = name {
+        println!("Differences in {}:", name.to_string_lossy());
+    }
+
+    for difference in differences {
+        println!("- {}", difference);
+    }
+
+    false
+}
+
+fn read_json(path: &Path) -> Option<Element> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            println!("Could not read {}: {}", path.to_string_lossy(), err);
+            return None;
+        },
+    };
+
+    let mut files = SourceFiles::new();
+    let file = files.add(path.to_path_buf(), source);
+
+    let lexer = LangLexer::new(files.source(file).expect("file was just added"));
+
+    match LangParser::new_with_file(lexer, file).parse_full() {
+        Ok(element) => Some(element),
+        Err(err) => {
+            print_diagnostic(&Diagnostic::from_parse_error(&err, file), &files);
+            None
+        },
+    }
+}
+
+fn load_registry_file(processor: &mut ElementProcessor<'_>, path: &Path) -> bool {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("Could not read registry file {}: {}", path.to_string_lossy(), err);
+            return false;
+        },
+    };
+
+    let mut files = SourceFiles::new();
+    let file = files.add(path.to_path_buf(), source);
+    let color = diagnostic::use_color(&std::io::stderr());
+
+    let lexer = LangLexer::new(files.source(file).expect("file was just added"));
+    let mut parser = LangParser::new_with_file(lexer, file);
+
+    let element = match parser.parse_full() {
+        Ok(element) => element,
+        Err(err) => {
+            eprintln!("{}", Diagnostic::from_parse_error(&err, file).render(&files, color));
+            return false;
+        },
+    };
+
+    if let Err(errors) = registry::load_registry(processor, &element) {
+        for error in errors {
+            eprintln!("{}", Diagnostic::from(&error).render(&files, color));
+        }
+
+        return false;
+    }
+
+    true
+}
@@ -0,0 +1,57 @@
+use crate::element::Element;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Declaration {
+    // import "path";
+    Import {
+        path: String,
+    },
+    // let name = value;
+    Constant {
+        name: String,
+        value: Element,
+    },
+    // fn name(parameters) = body;
+    Function {
+        name: String,
+        parameters: Vec<String>,
+        body: Element,
+    },
+}
+
+impl Declaration {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Declaration::Import { .. } => None,
+            Declaration::Constant { name, .. } => Some(name),
+            Declaration::Function { name, .. } => Some(name),
+        }
+    }
+}
+
+// A parsed source file: declarations, followed by the top level element.
+// Files without a top level element only provide definitions for other files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Module {
+    pub declarations: Vec<Declaration>,
+    pub element: Option<Element>,
+}
+
+impl Module {
+    pub fn new(declarations: Vec<Declaration>, element: Option<Element>) -> Module {
+        Module {
+            declarations, element
+        }
+    }
+
+    pub fn from_element(element: Element) -> Module {
+        Module::new(Vec::new(), Some(element))
+    }
+
+    pub fn imports(&self) -> impl Iterator<Item = &str> {
+        self.declarations.iter().filter_map(|declaration| match declaration {
+            Declaration::Import { path } => Some(path.as_str()),
+            _ => None,
+        })
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::element::Element;
+use crate::{format, io, object_element, string_element};
+use crate::io::FileResult;
+use crate::parser::Span;
+use crate::zip::ZipWriter;
+
+// Minecraft 1.21
+pub const DEFAULT_PACK_FORMAT: i32 = 48;
+
+pub struct PackOptions {
+    pub pack_format: i32,
+    pub description: String,
+    pub namespace: String,
+}
+
+impl PackOptions {
+    pub fn new() -> PackOptions {
+        PackOptions {
+            pack_format: DEFAULT_PACK_FORMAT,
+            description: String::from("Density functions"),
+            namespace: String::from("minecraft"),
+        }
+    }
+}
+
+impl Default for PackOptions {
+    fn default() -> Self {
+        PackOptions::new()
+    }
+}
+
+pub fn is_namespace(namespace: &str) -> bool {
+    !namespace.is_empty() && namespace.chars().all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-' | '.'))
+}
+
+// Source files are compiled, all other files are copied into the pack as they are
+pub fn is_source_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "json")
+}
+
+fn entry_name(file: &Path) -> String {
+    let components: Vec<_> = file.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect();
+
+    components.join("/")
+}
+
+// The path of a file in the pack. Compiled files that are already in a `data` folder and copied
+// files keep their path, other compiled files are density functions of the pack's namespace.
+pub fn pack_path(file: &Path, options: &PackOptions) -> String {
+    if !is_source_file(file) || file.starts_with("data") {
+        entry_name(file)
+    } else {
+        format!("data/{}/worldgen/density_function/{}", options.namespace, entry_name(file))
+    }
+}
+
+pub fn pack_mcmeta(options: &PackOptions) -> String {
+    let pack = object_element![
+        string_element!("pack") => object_element![
+            string_element!("pack_format") => Element::IntElement(options.pack_format, Span::none()),
+            string_element!("description") => string_element!(options.description.clone())
+        ]
+    ];
+
+    format::format_json(pack, format::Options::default()).unwrap_or_else(|_| unreachable!("pack.mcmeta only contains JSON"))
+}
+
+// Like `io::process_parallel`, but writes a data pack zip instead of a folder. The zip is only
+// written when all files compiled. A `pack.mcmeta` in the input folder replaces the generated one.
+pub fn process_to_zip<F>(input: PathBuf, output: PathBuf, threads: usize, options: &PackOptions, f: &F)
+    where F: Fn(&Path, String) -> (FileResult, String) + Sync {
+    // A single file is compiled into a pack of its own
+    let (input, files) = match (input.is_dir(), input.parent(), input.file_name()) {
+        (true, ..) => match io::list_files(&input) {
+            Ok(files) => (input.as_path(), files),
+            Err(err) => {
+                println!("{}", err);
+                return;
+            },
+        },
+        (false, Some(parent), Some(name)) if input.is_file() => (parent, vec![PathBuf::from(name)]),
+        _ => {
+            println!("{}", io::Error::UnknownFile(input));
+            return;
+        },
+    };
+
+    write_zip(input, &files, output, threads, options, f)
+}
+
+fn write_zip<F>(input: &Path, files: &[PathBuf], output: PathBuf, threads: usize, options: &PackOptions, f: &F)
+    where F: Fn(&Path, String) -> (FileResult, String) + Sync {
+    let mut zip = ZipWriter::new();
+    let (sources, assets): (Vec<PathBuf>, Vec<PathBuf>) = files.iter().cloned().partition(|file| is_source_file(file));
+
+    if !assets.iter().any(|file| file == Path::new("pack.mcmeta")) {
+        zip.add("pack.mcmeta", pack_mcmeta(options).as_bytes()).expect("first entry fits");
+    }
+
+    let mut compiled = 0;
+    let mut entries = HashMap::new();
+    let mut failed = io::process_files(input, &sources, threads, f, |file, output_str| {
+        add_entry(&mut zip, &mut entries, pack_path(file, options), file, output_str.as_bytes())?;
+        compiled += 1;
+        Ok(())
+    }, &mut std::io::stdout());
+
+    for file in &assets {
+        let result = std::fs::read(input.join(file)).map_err(io::Error::from)
+            .and_then(|contents| add_entry(&mut zip, &mut entries, entry_name(file), file, &contents));
+
+        if let Err(err) = result {
+            println!("Could not copy {}:\n- {}", file.to_string_lossy(), err);
+            failed.push(file);
+        }
+    }
+
+    println!();
+
+    if !failed.is_empty() {
+        io::write_summary(&mut std::io::stdout(), &failed, files.len());
+        println!("The data pack was not written.");
+        return;
+    }
+
+    let written = zip.finish().map_err(io::Error::from)
+        .and_then(|data| Ok(std::fs::write(&output, data)?));
+
+    match written {
+        Ok(()) => println!("Done. Wrote {} ({} compiled, {} copied).", output.to_string_lossy(), compiled, assets.len()),
+        Err(err) => println!("Could not write {}: {}", output.to_string_lossy(), err),
+    }
+}
+
+// Two files can end up at the same path in the pack, like `foo.json` and
+// `data/minecraft/worldgen/density_function/foo.json`. Only one of them could be read from the zip.
+fn add_entry(zip: &mut ZipWriter, entries: &mut HashMap<String, PathBuf>, name: String, file: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    if zip.contains(&name) {
+        let first = entries.get(&name).cloned().unwrap_or_else(|| PathBuf::from(&name)); // Generated files have no source
+        return Err(io::Error::DuplicateEntry(name, first, file.to_path_buf()));
+    }
+
+    zip.add(&name, contents)?;
+    entries.insert(name, file.to_path_buf());
+    Ok(())
+}
@@ -2,7 +2,9 @@ use std::fmt::{Display, Formatter};
 
 pub mod precedence;
 
-use crate::element::Element;
+use crate::element::{Element, SplinePoint};
+use crate::module::{Declaration, Module};
+use crate::parser::{FileId, Span};
 use crate::parser::lexer::{LangLexer, LangToken, LangTokenType, LexerError, TokenPos};
 use crate::util;
 use crate::util::EscapeError;
@@ -31,6 +33,11 @@ pub enum ParseError {
         got: Element,
     },
 
+    UnterminatedString(TokenPos),
+    UnterminatedComment(TokenPos),
+    TooDeeplyNested(TokenPos),
+    TooManyOperators(TokenPos),
+
     OtherError(TokenPos, String)
 }
 
@@ -40,6 +47,8 @@ impl From<LexerError> for ParseError { // To use ? operator on lexer methods
             LexerError::UnexpectedEof => ParseError::UnexpectedEof,
             LexerError::UnexpectedCharacter(pos, c) => ParseError::UnexpectedCharacter(pos, c),
             LexerError::ExpectedCharacter { pos, expected, got } => ParseError::ExpectedCharacter { pos, expected, got },
+            LexerError::UnterminatedString(pos) => ParseError::UnterminatedString(pos),
+            LexerError::UnterminatedComment(pos) => ParseError::UnterminatedComment(pos),
 
             LexerError::OtherError(pos, message) => ParseError::OtherError(pos, message)
         }
@@ -52,32 +61,104 @@ impl ParseError {
             EscapeError::UnexpectedEof => ParseError::UnexpectedEof,
             EscapeError::UnexpectedCharacter(pos_i, c) => ParseError::UnexpectedCharacter(
                 TokenPos::new(pos.line, pos.column + pos_i), c),
-            EscapeError::FailedConversion(err) => ParseError::OtherError(pos, format!("Failed conversion to u32: {}", err.to_string())),
-            EscapeError::InvalidCharacter(err) => ParseError::OtherError(pos, format!("Invalid character: {}", err.to_string())),
+            EscapeError::FailedConversion(err) => ParseError::OtherError(pos, format!("Failed conversion to u32: {}", err)),
+            EscapeError::InvalidCharacter(err) => ParseError::OtherError(pos, format!("Invalid character: {}", err)),
         }
     }
 }
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.pos() {
+            Some(pos) => write!(f, "{0} {1}", pos, self.message()),
+            None => f.write_str(&self.message()),
+        }
+    }
+}
+
+impl ParseError {
+    pub fn pos(&self) -> Option<TokenPos> {
+        match self {
+            ParseError::UnexpectedCharacter(pos, _)
+            | ParseError::ExpectedCharacter { pos, .. }
+            | ParseError::UnexpectedToken(pos, _)
+            | ParseError::ExpectedToken { pos, .. }
+            | ParseError::UnterminatedString(pos)
+            | ParseError::UnterminatedComment(pos)
+            | ParseError::TooDeeplyNested(pos)
+            | ParseError::TooManyOperators(pos)
+            | ParseError::OtherError(pos, _) => Some(*pos),
+            ParseError::UnexpectedEof | ParseError::UnexpectedElement { .. } => None,
+        }
+    }
+
+    // The message without the position
+    pub fn message(&self) -> String {
+        match self {
+            ParseError::UnexpectedEof => String::from("Unexpected EOF"),
+            ParseError::UnexpectedCharacter(_, c) => format!("Unexpected character: '{0}'", c),
+            ParseError::ExpectedCharacter { expected, got, .. } => format!("Expected character '{0}', got: '{1}'", expected, got),
+            ParseError::UnexpectedToken(_, token) => format!("Unexpected token: {0}", token),
+            ParseError::ExpectedToken { expected, got, .. } => format!("Expected token type {0:?}, got: {1}", expected, got),
+            ParseError::UnexpectedElement { expected, got } => format!("Expected token type {0:?}, got: {1:?}", expected, got),
+            ParseError::UnterminatedString(_) => String::from("Unterminated string"),
+            ParseError::UnterminatedComment(_) => String::from("Unterminated block comment"),
+            ParseError::TooDeeplyNested(_) => format!("Elements are nested more than {0} levels deep", MAX_DEPTH),
+            ParseError::TooManyOperators(_) => format!("Expressions can have at most {0} operators", MAX_OPERATORS),
+            ParseError::OtherError(_, message) => format!("Other error: {0}", message),
+        }
+    }
+
+    // Spans in the file that was parsed; tokens are underlined completely
+    pub fn span(&self, file: FileId) -> Option<Span> {
+        match self {
+            ParseError::UnexpectedToken(_, token) | ParseError::ExpectedToken { got: token, .. } => Some(token_span(file, token)),
+            ParseError::UnexpectedElement { got, .. } => Some(got.span()).filter(|span| !span.is_none()),
+            _ => self.pos().map(|pos| Span::new(file, pos, TokenPos::new(pos.line, pos.column + 1))),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
         match self {
-            ParseError::UnexpectedEof => f.write_str("Unexpected EOF"),
-            ParseError::UnexpectedCharacter(pos, c) => write!(f, "{0} Unexpected character: '{1}'", pos, c),
-            ParseError::ExpectedCharacter { pos, expected, got } =>
-                write!(f, "{0} Expected character '{1}', got: '{2}'", pos, expected, got),
-            ParseError::UnexpectedToken(pos, token) =>
-                write!(f, "{0} Unexpected token: {1}", pos, token),
-            ParseError::ExpectedToken { pos, expected, got } =>
-                write!(f, "{0} Expected token type {1:?}, got: {2}", pos, expected, got),
-            ParseError::UnexpectedElement { expected, got } =>
-                write!(f, "Expected token type {0:?}, got: {1:?}", expected, got),
-            ParseError::OtherError(pos, message) => write!(f, "{0} Other error: {1}", pos, message),
+            ParseError::UnexpectedEof => "E0001",
+            ParseError::UnexpectedCharacter(..) => "E0002",
+            ParseError::ExpectedCharacter { .. } => "E0003",
+            ParseError::UnexpectedToken(..) => "E0004",
+            ParseError::ExpectedToken { .. } => "E0005",
+            ParseError::UnexpectedElement { .. } => "E0006",
+            ParseError::OtherError(..) => "E0007",
+            ParseError::UnterminatedString(..) => "E0008",
+            ParseError::UnterminatedComment(..) => "E0009",
+            ParseError::TooDeeplyNested(..) => "E0010",
+            ParseError::TooManyOperators(..) => "E0011",
         }
     }
 }
 
+fn token_span(file: FileId, token: &LangToken) -> Span {
+    let pos = *token.pos();
+    let length = token.text().chars().count() as i32;
+
+    if token.token_type() == LangTokenType::LiteralString { // The position is after the leading '"'
+        Span::new(file, TokenPos::new(pos.line, pos.column - 1), TokenPos::new(pos.line, pos.column + length + 1))
+    } else {
+        Span::new(file, pos, TokenPos::new(pos.line, pos.column + length))
+    }
+}
+
+// Deeper elements would overflow the stack when they are parsed, processed or formatted
+pub const MAX_DEPTH: usize = 128;
+
+// Every binary operator nests its left operand one level deeper, but takes less stack than
+// elements in brackets, so they are limited separately
+pub const MAX_OPERATORS: usize = 256;
+
 pub struct LangParser<'a> {
     lexer: LangLexer<'a>,
+    file: FileId, // For the spans of the elements
+    errors: Vec<ParseError>, // Errors that parsing recovered from
+    depth: usize, // How deep the element that is being parsed is nested
+    operators: usize, // How many binary operators the element that is being parsed is nested in
 
     previous: LangToken, current: LangToken, // This is the reason LangToken can't store a reference
 }
@@ -86,8 +167,16 @@ type ParseResult<T> = Result<T, ParseError>;
 
 impl<'a> LangParser<'a> {
     pub fn new(lexer: LangLexer<'a>) -> Self {
+        Self::new_with_file(lexer, FileId(0))
+    }
+
+    pub fn new_with_file(lexer: LangLexer<'a>, file: FileId) -> Self {
         let mut parser = LangParser {
             lexer,
+            file,
+            errors: Vec::new(),
+            depth: 0,
+            operators: 0,
 
             previous: LangToken::empty(), current: LangToken::empty()
         };
@@ -108,9 +197,17 @@ impl<'a> LangParser<'a> {
 
     fn consume(&mut self) -> ParseResult<()> {
         std::mem::swap(&mut self.previous, &mut self.current); // self.previous = self.current; cannot move
-        self.current = self.lexer.scan_token()?; // Set current to next token;
 
-        Ok(())
+        // Invalid characters are skipped, the lexer always moves on after an error
+        loop {
+            match self.lexer.scan_token() {
+                Ok(token) => {
+                    self.current = token;
+                    return Ok(());
+                },
+                Err(err) => self.report(err.into()),
+            }
+        }
     }
 
     fn expect(&mut self, token_type: LangTokenType) -> ParseResult<&LangToken> {
@@ -123,117 +220,283 @@ impl<'a> LangParser<'a> {
         }
     }
 
-    fn parse_number(token: &LangToken) -> ParseResult<Element> {
+    fn token_span(&self, token: &LangToken) -> Span {
+        token_span(self.file, token)
+    }
+
+    // The span from `start` to the end of the previous token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.token_span(&self.previous))
+    }
+
+    fn parse_number(token: &LangToken, span: Span) -> ParseResult<Element> {
         token.text().parse::<i32>()
-            .map(|value| Element::IntElement(value))
+            .map(|value| Element::IntElement(value, span))
             .or_else(|_err| token.text().parse::<f64>()
-                .map(|value| Element::FloatElement(value)))
+                .map(|value| Element::FloatElement(value, span)))
             .map_err(|err| ParseError::OtherError(*token.pos(),
-                format!("Float parse error: {}", err.to_string())))
+                format!("Float parse error: {}", err)))
     }
 
-    fn parse_object(&mut self) -> ParseResult<Element> {
-        // Can use self.previous to see the object begin token ('{')
+    // Errors at the same position as the previous one are follow-up errors of it
+    fn report(&mut self, error: ParseError) {
+        if error.pos().is_none() || self.errors.last().and_then(ParseError::pos) != error.pos() {
+            self.errors.push(error);
+        }
+    }
 
-        let mut fields = Vec::new();
+    // Tokens that end a list, also if they belong to an enclosing one
+    fn is_list_end(&self) -> bool {
+        matches!(self.peek().token_type(), LangTokenType::ObjectEnd | LangTokenType::ArrayEnd | LangTokenType::GroupEnd
+            | LangTokenType::Semicolon | LangTokenType::Eof)
+    }
+
+    // Skips tokens until the next comma or the end of the list, so that parsing can continue
+    // after a syntax error. Brackets opened in between are skipped completely.
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+
+        loop {
+            match self.peek().token_type() {
+                LangTokenType::Eof => return,
+                LangTokenType::Comma | LangTokenType::Semicolon if depth == 0 => return,
+                LangTokenType::ObjectEnd | LangTokenType::ArrayEnd | LangTokenType::GroupEnd if depth == 0 => return,
+                LangTokenType::ObjectBegin | LangTokenType::ArrayBegin | LangTokenType::GroupBegin => depth += 1,
+                LangTokenType::ObjectEnd | LangTokenType::ArrayEnd | LangTokenType::GroupEnd => depth -= 1,
+                _ => {},
+            }
 
-        while self.peek().token_type() != LangTokenType::ObjectEnd && !self.is_eof() {
-            let key = self.parse_precedence(Precedence::Any)?;
-            self.expect(LangTokenType::Colon)?;
-            let value = self.parse_precedence(Precedence::Any)?;
+            let _ = self.consume();
+        }
+    }
+
+    // The span of the skipped tokens, or of the token where the error is if nothing was skipped
+    fn error_span(&self, start: Span) -> Span {
+        let end = self.token_span(&self.previous).end;
+
+        if (end.line, end.column) > (start.start.line, start.start.column) {
+            Span::new(self.file, start.start, end)
+        } else {
+            start
+        }
+    }
 
-            fields.push((key, value));
+    // Records the error and skips the rest of the element that failed to parse
+    fn recover(&mut self, error: ParseError, start: Span) -> Span {
+        self.report(error);
+        self.synchronize();
+        self.error_span(start)
+    }
 
-            let comma = self.expect(LangTokenType::Comma).map(|x| x.clone());
+    // Parses comma separated items up to the `end` token. Items that fail to parse are replaced
+    // by `error_item`, and parsing continues after the next comma.
+    fn parse_list<T>(&mut self, end: LangTokenType, parse_item: impl Fn(&mut Self) -> ParseResult<T>,
+                     error_item: impl Fn(Span) -> Option<T>) -> Vec<T> {
+        let mut items = Vec::new();
+
+        while !self.is_list_end() {
+            let start = self.token_span(&self.current);
+
+            match parse_item(self) {
+                Ok(item) => items.push(item),
+                Err(err) => {
+                    let span = self.recover(err, start);
+                    items.extend(error_item(span));
+                },
+            }
 
-            if self.peek().token_type() != LangTokenType::ObjectEnd {
-                comma?; // If the next token is not ')', this will report an error if the comma is missing
+            if self.peek().token_type() == LangTokenType::Comma {
+                let _ = self.consume();
+            } else if !self.is_list_end() { // The next item is parsed as if the comma was there
+                self.report(ParseError::ExpectedToken { pos: *self.current.pos(), expected: LangTokenType::Comma, got: self.current.clone() });
             }
         }
 
-        let _ = self.consume(); // ObjectEnd
-        Ok(Element::ObjectElement(fields))
+        if let Err(err) = self.expect(end) {
+            self.report(err);
+        }
+
+        items
     }
 
-    fn parse_array(&mut self) -> ParseResult<Element> {
-        let mut elements = Vec::new();
+    fn parse_object(&mut self) -> ParseResult<Element> {
+        // Can use self.previous to see the object begin token ('{')
+        let start = self.token_span(&self.previous);
 
-        while self.peek().token_type() != LangTokenType::ArrayEnd && !self.is_eof() {
-            elements.push(self.parse_precedence(Precedence::Any)?);
+        let fields = self.parse_list(LangTokenType::ObjectEnd, |parser| {
+            let key = parser.parse_precedence(Precedence::Any)?;
+            parser.expect(LangTokenType::Colon)?;
+            let value = parser.parse_precedence(Precedence::Any)?;
 
-            let comma = self.expect(LangTokenType::Comma).map(|x| x.clone());
+            Ok((key, value))
+        }, |span| Some((Element::ErrorElement(span), Element::ErrorElement(span))));
 
-            if self.peek().token_type() != LangTokenType::ArrayEnd {
-                comma?; // If the next token is not ')', this will report an error if the comma is missing
-            }
-        }
+        Ok(Element::ObjectElement(fields, self.span_from(start)))
+    }
 
-        let _ = self.consume(); // ArrayEnd
-        Ok(Element::ArrayElement(elements))
+    fn parse_array(&mut self) -> ParseResult<Element> {
+        let start = self.token_span(&self.previous);
+        let elements = self.parse_list(LangTokenType::ArrayEnd,
+            |parser| parser.parse_precedence(Precedence::Any), |span| Some(Element::ErrorElement(span)));
+
+        Ok(Element::ArrayElement(elements, self.span_from(start)))
     }
 
     fn parse_group(&mut self) -> ParseResult<Element> {
-        let element = self.parse_precedence(Precedence::Any)?;
-        self.expect(LangTokenType::GroupEnd)?;
+        let start = self.token_span(&self.previous);
+        let inner_start = self.token_span(&self.current);
+
+        let element = match self.parse_precedence(Precedence::Any) {
+            Ok(element) => element,
+            Err(err) => Element::ErrorElement(self.recover(err, inner_start)),
+        };
+
+        if let Err(err) = self.expect(LangTokenType::GroupEnd) {
+            self.report(err);
+            self.synchronize();
+            let _ = self.expect(LangTokenType::GroupEnd);
+        }
 
-        Ok(element)
+        Ok(element.with_span(self.span_from(start))) // Include the parentheses
     }
 
     fn parse_element(&mut self) -> ParseResult<Element> {
+        // Not consumed, so that error recovery can continue at these tokens
+        if matches!(self.peek().token_type(), LangTokenType::ObjectEnd | LangTokenType::ArrayEnd | LangTokenType::GroupEnd
+                | LangTokenType::Comma | LangTokenType::Semicolon) {
+            return Err(ParseError::UnexpectedToken(*self.current.pos(), self.current.clone()));
+        }
+
         self.consume()?;
+        let span = self.token_span(&self.previous);
 
         match self.previous.token_type() {
-            LangTokenType::Name => Ok(Element::NameElement(String::from(self.previous.text()))),
+            LangTokenType::Name => Ok(Element::NameElement(String::from(self.previous.text()), span)),
             LangTokenType::LiteralString => Ok(Element::StringElement(util::unescape_str(self.previous.text())
-                .map_err(|err| ParseError::from_escape_error(err, *self.previous.pos()))?)),
-            LangTokenType::LiteralNumber => LangParser::parse_number(&self.previous),
-            LangTokenType::LiteralTrue => Ok(Element::BooleanElement(true)),
-            LangTokenType::LiteralFalse => Ok(Element::BooleanElement(false)),
-            LangTokenType::LiteralNull => Ok(Element::NullElement),
+                .map_err(|err| ParseError::from_escape_error(err, *self.previous.pos()))?, span)),
+            LangTokenType::LiteralNumber => LangParser::parse_number(&self.previous, span),
+            LangTokenType::LiteralTrue => Ok(Element::BooleanElement(true, span)),
+            LangTokenType::LiteralFalse => Ok(Element::BooleanElement(false, span)),
+            LangTokenType::LiteralNull => Ok(Element::NullElement(span)),
             LangTokenType::ObjectBegin => self.parse_object(),
             LangTokenType::ArrayBegin => self.parse_array(),
             LangTokenType::GroupBegin => self.parse_group(),
+            LangTokenType::Minus => self.parse_unary(),
+            LangTokenType::If => self.parse_conditional(),
+            LangTokenType::Spline => self.parse_spline(),
 
             LangTokenType::Eof => Err(ParseError::UnexpectedEof),
             _ => Err(ParseError::UnexpectedToken(*self.previous.pos(), self.previous.clone())),
         }
     }
 
+    fn parse_unary(&mut self) -> ParseResult<Element> {
+        let op = self.previous.clone();
+        let start = self.token_span(&op);
+
+        let operand = self.parse_precedence(Precedence::Unary)?;
+        let span = start.to(operand.span());
+
+        match operand {
+            // Negative number literals are not a separate token, so they are folded here
+            Element::IntElement(value, _) => Ok(Element::IntElement(-value, span)),
+            Element::FloatElement(value, _) => Ok(Element::FloatElement(-value, span)),
+            operand => Ok(Element::UnaryElement {
+                operator: op,
+                operand: Box::new(operand),
+                span,
+            }),
+        }
+    }
+
+    fn parse_conditional(&mut self) -> ParseResult<Element> {
+        let start = self.token_span(&self.previous);
+        let condition = self.parse_precedence(Precedence::Any)?;
+        self.expect(LangTokenType::Then)?;
+        let when_true = self.parse_precedence(Precedence::Any)?;
+        self.expect(LangTokenType::Else)?;
+        let when_false = self.parse_precedence(Precedence::Any)?;
+
+        Ok(Element::ConditionalElement {
+            span: start.to(when_false.span()),
+            condition: Box::new(condition),
+            when_true: Box::new(when_true),
+            when_false: Box::new(when_false),
+        })
+    }
+
+    fn parse_spline(&mut self) -> ParseResult<Element> {
+        let start = self.token_span(&self.previous);
+        self.expect(LangTokenType::GroupBegin)?;
+        let coordinate = self.parse_precedence(Precedence::Any)?;
+        self.expect(LangTokenType::GroupEnd)?;
+        self.expect(LangTokenType::ObjectBegin)?;
+
+        let points = self.parse_list(LangTokenType::ObjectEnd, |parser| {
+            let location = parser.parse_precedence(Precedence::Any)?;
+            parser.expect(LangTokenType::Colon)?;
+            let value = parser.parse_precedence(Precedence::Any)?;
+
+            let derivative = if parser.expect(LangTokenType::Quote).is_ok() {
+                Some(parser.parse_precedence(Precedence::Any)?)
+            } else {
+                None
+            };
+
+            Ok(SplinePoint { location, value, derivative })
+        }, |span| Some(SplinePoint { location: Element::ErrorElement(span), value: Element::ErrorElement(span), derivative: None }));
+
+        Ok(Element::SplineElement {
+            coordinate: Box::new(coordinate),
+            points,
+            span: self.span_from(start),
+        })
+    }
+
     fn parse_binary(&mut self, left: Element, precedence: Precedence) -> ParseResult<Element> {
         let op = self.previous.clone();
 
         let right = self.parse_precedence(precedence)?;
 
         Ok(Element::BinaryElement {
+            span: left.span().to(right.span()),
             left: Box::new(left),
             operator: op,
             right: Box::new(right)
         })
     }
 
+    // Expects the GroupBegin token ('(') to already be matched
+    fn parse_arguments(&mut self) -> ParseResult<Vec<Element>> {
+        Ok(self.parse_list(LangTokenType::GroupEnd, |parser| {
+            match parser.parse_precedence(Precedence::Any)? {
+                Element::NameElement(name, start) if parser.peek().token_type() == LangTokenType::Colon => { // Named argument
+                    parser.consume()?;
+                    let value = parser.parse_precedence(Precedence::Any)?;
+
+                    Ok(Element::NamedArgumentElement { name, span: start.to(value.span()), value: Box::new(value) })
+                },
+                argument => Ok(argument),
+            }
+        }, |span| Some(Element::ErrorElement(span))))
+    }
+
     fn parse_member(&mut self, left: Element) -> ParseResult<Element> {
         self.expect(LangTokenType::Name)?;
         let name = self.previous.text().to_string();
 
-        if let Ok(_) = self.expect(LangTokenType::GroupBegin) { // Function call
-            let mut arguments = Vec::new();
-
-            while self.peek().token_type() != LangTokenType::GroupEnd && !self.is_eof() {
-                arguments.push(self.parse_precedence(Precedence::Any)?);
-
-                let comma = self.expect(LangTokenType::Comma).map(|x| x.clone());
-
-                if self.peek().token_type() != LangTokenType::GroupEnd { comma?; }
-            }
-            let _ = self.consume(); // GroupEnd
+        if self.expect(LangTokenType::GroupBegin).is_ok() { // Function call
+            let arguments = self.parse_arguments()?;
 
             Ok(Element::FunctionCallElement {
+                span: self.span_from(left.span()),
                 receiver: Some(Box::new(left)),
                 name,
                 arguments: Some(arguments)
             })
         } else {
             Ok(Element::FunctionCallElement {
+                span: self.span_from(left.span()),
                 receiver: Some(Box::new(left)),
                 name,
                 arguments: None,
@@ -242,25 +505,17 @@ impl<'a> LangParser<'a> {
     }
 
     fn parse_function(&mut self, left: Element) -> ParseResult<Element> {
-        if let Element::NameElement(name) = left {
-            let mut arguments = Vec::new();
-
-            while self.peek().token_type() != LangTokenType::GroupEnd && !self.is_eof() {
-                arguments.push(self.parse_precedence(Precedence::Any)?);
-
-                let comma = self.expect(LangTokenType::Comma).map(|x| x.clone());
-
-                if self.peek().token_type() != LangTokenType::GroupEnd { comma?; }
-            }
-            let _ = self.consume(); // GroupEnd
+        if let Element::NameElement(name, start) = left {
+            let arguments = self.parse_arguments()?;
 
             Ok(Element::FunctionCallElement {
                 receiver: None,
                 name,
-                arguments: Some(arguments)
+                arguments: Some(arguments),
+                span: self.span_from(start),
             })
         } else {
-            return Err(ParseError::UnexpectedElement {
+            Err(ParseError::UnexpectedElement {
                 expected: LangTokenType::Name,
                 got: left
             })
@@ -268,9 +523,45 @@ impl<'a> LangParser<'a> {
     }
 
     pub fn parse_precedence(&mut self, precedence: Precedence) -> ParseResult<Element> {
+        let (depth, operators) = (self.depth, self.operators);
+        let result = self.parse_nested(precedence);
+        (self.depth, self.operators) = (depth, operators);
+        result
+    }
+
+    // Counts one level for the element and one for every member access or call applied to it,
+    // as each of them nests the element further
+    fn enter_nested(&mut self) -> ParseResult<()> {
+        self.depth += 1;
+
+        if self.depth > MAX_DEPTH {
+            Err(ParseError::TooDeeplyNested(*self.current.pos()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn enter_operator(&mut self) -> ParseResult<()> {
+        self.operators += 1;
+
+        if self.operators > MAX_OPERATORS {
+            Err(ParseError::TooManyOperators(*self.current.pos()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn parse_nested(&mut self, precedence: Precedence) -> ParseResult<Element> {
+        self.enter_nested()?;
         let mut left = self.parse_element()?;
 
-        while self.get_precedence().map(|x| precedence < x).unwrap_or(false) {
+        while let Some(operator_precedence) = self.get_precedence().filter(|x| precedence < *x) {
+            if operator_precedence == Precedence::Call {
+                self.enter_nested()?;
+            } else {
+                self.enter_operator()?;
+            }
+
             self.consume()?;
 
             match self.previous.token_type() {
@@ -279,6 +570,10 @@ impl<'a> LangParser<'a> {
                 LangTokenType::Star => left = self.parse_binary(left, Precedence::Factor)?,
                 LangTokenType::Slash => left = self.parse_binary(left, Precedence::Factor)?,
 
+                LangTokenType::Arrow => left = self.parse_binary(left, Precedence::Arrow)?,
+                LangTokenType::Less | LangTokenType::LessEqual | LangTokenType::Greater | LangTokenType::GreaterEqual =>
+                    left = self.parse_binary(left, Precedence::Comparison)?,
+
                 LangTokenType::Dot => left = self.parse_member(left)?,
                 LangTokenType::GroupBegin => left = self.parse_function(left)?,
 
@@ -290,9 +585,125 @@ impl<'a> LangParser<'a> {
         Ok(left)
     }
 
+    // Returns the first syntax error, see `parse_full_with_recovery` to get all of them
     pub fn parse_full(&mut self) -> ParseResult<Element> {
-        self.parse_precedence(Precedence::Any)
-        // TODO Disallow any tokens after top level element
+        let (element, errors) = self.parse_full_with_recovery();
+        errors.into_iter().next().map_or(Ok(element), Err)
+    }
+
+    // Parts that fail to parse are replaced by error elements
+    pub fn parse_full_with_recovery(&mut self) -> (Element, Vec<ParseError>) {
+        let element = self.parse_top_level_element();
+        (element, std::mem::take(&mut self.errors))
+    }
+
+    // A file contains exactly one top level element, anything after it is an error
+    fn parse_top_level_element(&mut self) -> Element {
+        let start = self.token_span(&self.current);
+
+        let element = match self.parse_precedence(Precedence::Any) {
+            Ok(element) => element,
+            Err(err) => {
+                self.report(err);
+                Element::ErrorElement(self.error_span(start))
+            },
+        };
+
+        if !self.is_eof() {
+            self.report(ParseError::UnexpectedToken(*self.current.pos(), self.current.clone()));
+        }
+
+        element
+    }
+
+    fn parse_import(&mut self) -> ParseResult<Declaration> {
+        self.expect(LangTokenType::LiteralString)?;
+        let path = util::unescape_str(self.previous.text())
+            .map_err(|err| ParseError::from_escape_error(err, *self.previous.pos()))?;
+        self.expect(LangTokenType::Semicolon)?;
+
+        Ok(Declaration::Import { path })
+    }
+
+    fn parse_let(&mut self) -> ParseResult<Declaration> {
+        self.expect(LangTokenType::Name)?;
+        let name = self.previous.text().to_string();
+
+        self.expect(LangTokenType::Equals)?;
+        let value = self.parse_precedence(Precedence::Any)?;
+        self.expect(LangTokenType::Semicolon)?;
+
+        Ok(Declaration::Constant { name, value })
+    }
+
+    fn parse_fn(&mut self) -> ParseResult<Declaration> {
+        self.expect(LangTokenType::Name)?;
+        let name = self.previous.text().to_string();
+
+        self.expect(LangTokenType::GroupBegin)?;
+
+        let parameters = self.parse_list(LangTokenType::GroupEnd, |parser| {
+            parser.expect(LangTokenType::Name)?;
+            Ok(parser.previous.text().to_string())
+        }, |_| None);
+
+        self.expect(LangTokenType::Equals)?;
+        let body = self.parse_precedence(Precedence::Any)?;
+        self.expect(LangTokenType::Semicolon)?;
+
+        Ok(Declaration::Function { name, parameters, body })
+    }
+
+    // Parses a source file, which can start with declarations before the top level element.
+    // Returns the first syntax error, see `parse_module_with_recovery` to get all of them.
+    pub fn parse_module(&mut self) -> ParseResult<Module> {
+        let (module, errors) = self.parse_module_with_recovery();
+        errors.into_iter().next().map_or(Ok(module), Err)
+    }
+
+    // Declarations that fail to parse are left out, other parts are replaced by error elements
+    pub fn parse_module_with_recovery(&mut self) -> (Module, Vec<ParseError>) {
+        let mut declarations = Vec::new();
+
+        loop {
+            let declaration = match self.peek().token_type() {
+                LangTokenType::Import => self.consume().and_then(|_| self.parse_import()),
+                LangTokenType::Let => self.consume().and_then(|_| self.parse_let()),
+                LangTokenType::Fn => self.consume().and_then(|_| self.parse_fn()),
+                _ => break,
+            };
+
+            match declaration {
+                Ok(declaration) => declarations.push(declaration),
+                Err(err) => {
+                    self.report(err);
+                    self.skip_declaration();
+                },
+            }
+        }
+
+        if self.is_eof() && (!declarations.is_empty() || !self.errors.is_empty()) { // Only definitions for other files
+            return (Module::new(declarations, None), std::mem::take(&mut self.errors));
+        }
+
+        let element = self.parse_top_level_element();
+        (Module::new(declarations, Some(element)), std::mem::take(&mut self.errors))
+    }
+
+    // Skips to the start of the next declaration after a syntax error
+    fn skip_declaration(&mut self) {
+        loop {
+            match self.peek().token_type() {
+                LangTokenType::Eof | LangTokenType::Import | LangTokenType::Let | LangTokenType::Fn => return,
+                LangTokenType::Semicolon => {
+                    let _ = self.consume();
+                    return;
+                },
+                _ => {
+                    let _ = self.consume();
+                },
+            }
+        }
     }
 
     fn get_precedence(&self) -> Option<Precedence> {
@@ -302,6 +713,10 @@ impl<'a> LangParser<'a> {
             LangTokenType::Star => Some(Precedence::Factor),
             LangTokenType::Slash => Some(Precedence::Factor),
 
+            LangTokenType::Arrow => Some(Precedence::Arrow),
+            LangTokenType::Less | LangTokenType::LessEqual | LangTokenType::Greater | LangTokenType::GreaterEqual =>
+                Some(Precedence::Comparison),
+
             LangTokenType::Dot => Some(Precedence::Call),
             LangTokenType::GroupBegin => Some(Precedence::Call),
             _ => None,
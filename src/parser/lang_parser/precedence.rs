@@ -4,8 +4,11 @@ pub enum Precedence {
 
     Any, // Precedence of expressions in array, object, or group
 
+    Arrow,      // Mappings like `y -> value`
+    Comparison, // Conditions (`<`, `<=`, `>`, `>=`)
     Sum,    // Addition and subtraction
     Factor, // Multiplication and division
+    Unary,  // Negation
 
     Call, // Function call
 }
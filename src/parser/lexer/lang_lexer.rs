@@ -16,6 +16,14 @@ pub enum LangTokenType {
     LiteralFalse,
     LiteralNull,
 
+    Import,
+    Let,
+    Fn,
+    If,
+    Then,
+    Else,
+    Spline,
+
     ObjectBegin,
     ObjectEnd,
     ArrayBegin,
@@ -27,12 +35,23 @@ pub enum LangTokenType {
     Comma,
     Dot,
     Colon,
+    Semicolon,
+    Equals,
+    Quote,
+    Arrow,
+
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
 
     Plus,
     Minus,
     Star,
     Slash,
 
+    Comment, // Only produced when the lexer keeps comments
+
     Eof,
 }
 
@@ -43,7 +62,7 @@ pub struct LangToken {
     pos: TokenPos,
 }
 
-impl<'a> LangToken {
+impl LangToken {
     pub fn new(token_type: LangTokenType, text: String, pos: TokenPos) -> LangToken {
         LangToken {
             token_type, text, pos
@@ -76,6 +95,7 @@ impl Display for LangToken {
 
 pub struct LangLexer<'a> {
     lexer: Lexer<'a>,
+    keep_comments: bool,
 }
 
 type LexerResult<T> = Result<T, LexerError>;
@@ -83,7 +103,16 @@ type LexerResult<T> = Result<T, LexerError>;
 impl<'a> LangLexer<'a> {
     pub fn new(source: &'a str) -> LangLexer<'a> {
         LangLexer {
-            lexer: Lexer::new(source)
+            lexer: Lexer::new(source),
+            keep_comments: false,
+        }
+    }
+
+    // Returns comments as tokens instead of skipping them, for formatting source files
+    pub fn new_with_comments(source: &'a str) -> LangLexer<'a> {
+        LangLexer {
+            lexer: Lexer::new(source),
+            keep_comments: true,
         }
     }
 
@@ -94,6 +123,22 @@ impl<'a> LangLexer<'a> {
         LangToken::new(token_type, String::from(self.lexer.get_token_text()), self.lexer.pos())
     }
 
+    // Makes an `or_equal` token if the next character is '=', consuming it
+    fn make_comparison_token(&mut self, token_type: LangTokenType, or_equal: LangTokenType) -> LangToken {
+        if matches!(self.lexer.peek(), Ok('=')) {
+            let _ = self.lexer.consume();
+            self.make_token(or_equal)
+        } else {
+            self.make_token(token_type)
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        while self.lexer.peek().copied().is_ok_and(util::is_digit) {
+            let _ = self.lexer.consume();
+        }
+    }
+
     pub fn scan_token(&mut self) -> LexerResult<LangToken> {
         self.lexer.skip_whitespace();
         self.lexer.set_start_pos_to_current();
@@ -112,89 +157,78 @@ impl<'a> LangLexer<'a> {
             ')' => Ok(self.make_token(LangTokenType::GroupEnd)),
             ',' => Ok(self.make_token(LangTokenType::Comma)),
             ':' => Ok(self.make_token(LangTokenType::Colon)),
+            ';' => Ok(self.make_token(LangTokenType::Semicolon)),
+            '=' => Ok(self.make_token(LangTokenType::Equals)),
+            '\'' => Ok(self.make_token(LangTokenType::Quote)),
             '.' => Ok(self.make_token(LangTokenType::Dot)),
 
+            '<' => Ok(self.make_comparison_token(LangTokenType::Less, LangTokenType::LessEqual)),
+            '>' => Ok(self.make_comparison_token(LangTokenType::Greater, LangTokenType::GreaterEqual)),
+
             '+' => Ok(self.make_token(LangTokenType::Plus)),
-            '-' if !util::is_digit(*self.lexer.peek()?) => Ok(self.make_token(LangTokenType::Minus)),
+            '-' => {
+                if matches!(self.lexer.peek(), Ok('>')) {
+                    let _ = self.lexer.consume();
+                    Ok(self.make_token(LangTokenType::Arrow))
+                } else {
+                    Ok(self.make_token(LangTokenType::Minus)) // Negative numbers are handled by the parser
+                }
+            },
             '*' => Ok(self.make_token(LangTokenType::Star)),
             '/' => {
-                if let Ok(_) = self.lexer.skip_comment() {
-                    return self.scan_token(); // TODO better solution than recursive call
+                if matches!(self.lexer.peek(), Ok('/' | '*')) {
+                    self.lexer.skip_comment()?;
+
+                    if self.keep_comments {
+                        return Ok(self.make_token(LangTokenType::Comment));
+                    }
+
+                    self.scan_token() // TODO better solution than recursive call
                 } else {
                     Ok(self.make_token(LangTokenType::Slash))
                 }
             },
 
             '"' => {
+                let start = self.lexer.pos();
                 self.lexer.set_start_pos_to_current(); // Don't include leading '"'
 
-                while *self.lexer.peek()? != '"' {
-                    let _ = self.lexer.consume();
+                loop {
+                    match self.lexer.consume() {
+                        Ok('"') => break,
+                        Ok('\\') => { // Skip the escaped character, which might be '"'
+                            self.lexer.consume().map_err(|_| LexerError::UnterminatedString(start))?;
+                        },
+                        Ok(_) => {},
+                        Err(_) => return Err(LexerError::UnterminatedString(start)),
+                    }
                 }
 
-                self.lexer.consume()?; // the trailing '"'
                 let text = self.lexer.get_token_text();
 
                 Ok(LangToken::new(LangTokenType::LiteralString, String::from(&text[.. text.len() - 1]), self.lexer.pos()))
             },
 
-            '0'..='9' | '-' => {
-                while self.lexer.peek().map(|c| *c).map(util::is_digit).unwrap_or(false) {
-                    let _ = self.lexer.consume();
-                }
-
-                if let Ok(_) = self.lexer.expect('.') {
-                    let mut c = *match self.lexer.peek() {
-                        Ok(c) => c,
-                        Err(_) => return Ok(self.make_token(LangTokenType::LiteralNumber)),
-                    };
-
-                    if !util::is_digit(c) {
-                        // return Err(LexerError::UnexpectedCharacter(self.lexer.current_pos, c));
-
-                        self.lexer.hold('.'); // TODO Find a better solution for this
-                        self.lexer.current -= 1;
-
-                        return Ok(self.make_token(LangTokenType::LiteralNumber));
-                    }
+            '0'..='9' => {
+                self.skip_digits();
 
-                    while util::is_digit(c) {
-                        let _ = self.lexer.consume();
-
-                        c = *match self.lexer.peek() {
-                            Ok(c) => c,
-                            Err(_) => return Ok(self.make_token(LangTokenType::LiteralNumber)),
-                        };
-                    }
+                // A '.' without digits after it is a separate token, like in `1.abs()`
+                if matches!(self.lexer.peek(), Ok('.')) && self.lexer.peek_second().is_some_and(util::is_digit) {
+                    let _ = self.lexer.consume();
+                    self.skip_digits();
                 }
 
-                let next = *match self.lexer.peek() {
-                    Ok(c) => c,
-                    Err(_) => return Ok(self.make_token(LangTokenType::LiteralNumber)),
-                };
-
-                if next == 'e' || next == 'E' { // Exponent
+                if matches!(self.lexer.peek(), Ok('e' | 'E')) { // Exponent
                     let _ = self.lexer.consume();
-                    let next = *match self.lexer.peek() {
-                        Ok(c) => c,
-                        Err(_) => return Ok(self.make_token(LangTokenType::LiteralNumber)),
-                    };
 
-                    if next == '+' || next == '-' {
+                    if matches!(self.lexer.peek(), Ok('+' | '-')) {
                         let _ = self.lexer.consume();
                     }
 
-                    let next = *match self.lexer.peek() {
-                        Ok(c) => c,
-                        Err(_) => return Ok(self.make_token(LangTokenType::LiteralNumber)),
-                    };
-
-                    if !util::is_digit(next) {
-                        return Err(LexerError::UnexpectedCharacter(self.lexer.current_pos, next));
-                    }
-
-                    while self.lexer.peek().map(|c| *c).map(util::is_digit).unwrap_or(false) {
-                        let _ = self.lexer.consume();
+                    match self.lexer.peek() {
+                        Ok(&c) if util::is_digit(c) => self.skip_digits(),
+                        Ok(&c) => return Err(LexerError::UnexpectedCharacter(self.lexer.current_pos(), c)),
+                        Err(_) => return Err(LexerError::OtherError(self.lexer.pos(), String::from("Missing exponent in number"))),
                     }
                 }
 
@@ -202,7 +236,7 @@ impl<'a> LangLexer<'a> {
             },
 
             _ if util::is_alpha(c) => {
-                while self.lexer.peek().map(|c| *c).map(util::is_alpha_numeric).unwrap_or(false) {
+                while self.lexer.peek().copied().map(util::is_alpha_numeric).unwrap_or(false) {
                     let _ = self.lexer.consume();
                 }
 
@@ -212,6 +246,13 @@ impl<'a> LangLexer<'a> {
                     "true" => token.token_type = LangTokenType::LiteralTrue,
                     "false" => token.token_type = LangTokenType::LiteralFalse,
                     "null" => token.token_type = LangTokenType::LiteralNull,
+                    "import" => token.token_type = LangTokenType::Import,
+                    "let" => token.token_type = LangTokenType::Let,
+                    "fn" => token.token_type = LangTokenType::Fn,
+                    "if" => token.token_type = LangTokenType::If,
+                    "then" => token.token_type = LangTokenType::Then,
+                    "else" => token.token_type = LangTokenType::Else,
+                    "spline" => token.token_type = LangTokenType::Spline,
                     _ => {},
                 }
 
@@ -16,7 +16,7 @@ pub struct TokenPos {
 
 impl Display for TokenPos {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "[line {0} column {1}]", self.line, self.column)
+        write!(f, "[line {0} column {1}]", self.line + 1, self.column + 1) // 1-based, like editors
     }
 }
 
@@ -30,6 +30,8 @@ pub enum LexerError {
         expected: char,
         got: char
     },
+    UnterminatedString(TokenPos),
+    UnterminatedComment(TokenPos),
 
     OtherError(TokenPos, String)
 }
@@ -40,8 +42,8 @@ impl LexerError {
             EscapeError::UnexpectedEof => LexerError::UnexpectedEof,
             EscapeError::UnexpectedCharacter(pos_i, c) => LexerError::UnexpectedCharacter(
                 TokenPos::new(pos.line, pos.column + pos_i), c),
-            EscapeError::FailedConversion(err) => LexerError::OtherError(pos, format!("Failed conversion to u32: {}", err.to_string())),
-            EscapeError::InvalidCharacter(err) => LexerError::OtherError(pos, format!("Invalid character: {}", err.to_string())),
+            EscapeError::FailedConversion(err) => LexerError::OtherError(pos, format!("Failed conversion to u32: {}", err)),
+            EscapeError::InvalidCharacter(err) => LexerError::OtherError(pos, format!("Invalid character: {}", err)),
         }
     }
 }
@@ -59,8 +61,7 @@ impl TokenPos {
 struct Lexer<'a> {
     source: &'a str,
     source_chars: Peekable<Chars<'a>>,
-    hold: Option<char>,
-    start: usize, current: usize,
+    start: usize, current: usize, // Byte offsets into the source
     pos: TokenPos, current_pos: TokenPos,
 }
 
@@ -70,7 +71,7 @@ type LexerResult<T> = Result<T, LexerError>;
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a str) -> Lexer<'a> {
         Lexer {
-            source, source_chars: source.chars().peekable(), hold: None,
+            source, source_chars: source.chars().peekable(),
             start: 0, current: 0,
             pos: TokenPos::begin(), current_pos: TokenPos::begin()
         }
@@ -78,25 +79,19 @@ impl<'a> Lexer<'a> {
 
     pub fn pos(&self) -> TokenPos { self.pos }
 
-    pub fn hold(&mut self, character: char) {
-        self.hold = Some(character);
-    }
+    pub fn current_pos(&self) -> TokenPos { self.current_pos }
 
     pub fn peek(&mut self) -> LexerResult<&char> {
-        if let Some(c) = self.hold.as_ref() {
-            return Ok(c);
-        }
-
         self.source_chars.peek().ok_or(LexerError::UnexpectedEof)
     }
 
-    pub fn consume(&mut self) -> LexerResult<char> {
-        if let Some(c) = self.hold.take() {
-            self.current += 1;
-            return Ok(c);
-        }
+    // The character after the next one
+    pub fn peek_second(&self) -> Option<char> {
+        self.source[self.current..].chars().nth(1)
+    }
 
-        self.source_chars.next().ok_or(LexerError::UnexpectedEof).map(|c| {
+    pub fn consume(&mut self) -> LexerResult<char> {
+        self.source_chars.next().ok_or(LexerError::UnexpectedEof).inspect(|&c| {
             if c == '\n' {
                 self.current_pos.line += 1;
                 self.current_pos.column = 0;
@@ -104,8 +99,7 @@ impl<'a> Lexer<'a> {
                 self.current_pos.column += 1;
             }
 
-            self.current += 1;
-            c
+            self.current += c.len_utf8();
         })
     }
 
@@ -146,30 +140,26 @@ impl<'a> Lexer<'a> {
 
     // Expects the starting '/' of the comment to already be matched
     pub fn skip_comment(&mut self) -> LexerResult<()> {
-        if let Ok(_) = self.expect('/') {
+        if self.expect('/').is_ok() {
             self.skip_line();
             Ok(())
-        } else if let Ok(_) = self.expect('*') {
+        } else if self.expect('*').is_ok() {
             let mut comment_count = 1;
 
             loop {
-                let c = self.consume()?;
+                let c = self.consume().map_err(|_| LexerError::UnterminatedComment(self.pos))?;
 
                 match c {
-                    '/' => { // Allow nested comments
-                        if let Ok(_) = self.expect('*') {
-                            comment_count += 1;
-                        }
+                    '/' if self.expect('*').is_ok() => { // Allow nested comments
+                        comment_count += 1;
                     },
-                    '*' => {
-                        if let Ok(_) = self.expect('/') {
-                            comment_count -= 1;
+                    '*' if self.expect('/').is_ok() => {
+                        comment_count -= 1;
 
-                            if comment_count <= 0 {
-                                return Ok(());
-                            }
+                        if comment_count <= 0 {
+                            return Ok(());
                         }
-                    }
+                    },
                     _ => {}
                 }
             }
@@ -179,7 +169,7 @@ impl<'a> Lexer<'a> {
     }
 
     pub fn skip(&mut self, number: i32) -> LexerResult<()> {
-        for _ in [0..number].iter() {
+        for _ in 0..number {
             self.consume()?;
         }
 
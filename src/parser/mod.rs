@@ -1,5 +1,7 @@
 pub mod lexer;
 
 mod lang_parser;
+mod span;
 
-pub use lang_parser::*;
\ No newline at end of file
+pub use lang_parser::*;
+pub use span::{FileId, SourceFiles, Span};
\ No newline at end of file
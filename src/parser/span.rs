@@ -0,0 +1,88 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use crate::parser::lexer::TokenPos;
+
+// Index of a source file in `SourceFiles`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileId(pub u32);
+
+impl FileId {
+    // For elements that were not parsed from a file, like the ones created by built-in functions
+    pub const NONE: FileId = FileId(u32::MAX);
+}
+
+// The part of a source file that an element was parsed from. `end` is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub file: FileId,
+    pub start: TokenPos,
+    pub end: TokenPos,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: TokenPos, end: TokenPos) -> Span {
+        Span {
+            file, start, end
+        }
+    }
+
+    pub fn none() -> Span {
+        Span::new(FileId::NONE, TokenPos::begin(), TokenPos::begin())
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.file == FileId::NONE
+    }
+
+    // The span from the start of this one to the end of `other`
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.file, self.start, other.end)
+    }
+}
+
+impl Default for Span {
+    fn default() -> Self {
+        Span::none()
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start)
+    }
+}
+
+// Paths and contents of the files that spans refer to
+#[derive(Clone, Debug, Default)]
+pub struct SourceFiles {
+    paths: Vec<PathBuf>,
+    sources: Vec<String>,
+}
+
+impl SourceFiles {
+    pub fn new() -> SourceFiles {
+        SourceFiles {
+            paths: Vec::new(),
+            sources: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, path: PathBuf, source: String) -> FileId {
+        self.paths.push(path);
+        self.sources.push(source);
+        FileId(self.paths.len() as u32 - 1)
+    }
+
+    pub fn path(&self, file: FileId) -> Option<&Path> {
+        self.paths.get(file.0 as usize).map(PathBuf::as_path)
+    }
+
+    pub fn source(&self, file: FileId) -> Option<&str> {
+        self.sources.get(file.0 as usize).map(String::as_str)
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.paths.iter().map(PathBuf::as_path)
+    }
+}
@@ -1,33 +1,137 @@
 use crate::element::Element;
 use crate::{object_element, string_element};
-use crate::processor::{ProcessError, ProcessResult};
+use crate::parser::Span;
+use crate::parser::lexer::{LangToken, LangTokenType, TokenPos};
+use crate::processor::{ArgumentType, ProcessError, ProcessResult};
+
+// a - b => add(a, mul(b, -1)), or add(a, -b) if b is a number
+pub fn subtract(left: Element, right: Element) -> ProcessResult {
+    let right = match right {
+        Element::IntElement(value, span) => Element::IntElement(-value, span),
+        Element::FloatElement(value, span) => Element::FloatElement(-value, span),
+        right => object_element!(string_element!("type") => string_element!("minecraft:mul"),
+            string_element!("argument1") => right, string_element!("argument2") => Element::FloatElement(-1.0, Span::none())
+        ),
+    };
+
+    ProcessResult::from_element(object_element!(string_element!("type") => string_element!("minecraft:add"),
+        string_element!("argument1") => left, string_element!("argument2") => right
+    ))
+}
+
+// a / b => mul(a, 1 / b); there is no division density function, so b has to be a number
+pub fn divide(left: Element, right: Element) -> ProcessResult {
+    let (divisor, span) = match right {
+        Element::IntElement(value, span) => (value as f64, span),
+        Element::FloatElement(value, span) => (value, span),
+        right => return ProcessResult::new(Element::BinaryElement {
+            span: left.span().to(right.span()),
+            left: Box::new(left),
+            operator: LangToken::new(LangTokenType::Slash, String::from("/"), TokenPos::begin()),
+            right: Box::new(right.clone()),
+        }, Vec::new(), vec![ProcessError::NonConstantDivisor(right)]),
+    };
+
+    if divisor == 0.0 {
+        return ProcessResult::new(left, Vec::new(), vec![ProcessError::DivisionByZero(span)]);
+    }
+
+    ProcessResult::from_element(object_element!(string_element!("type") => string_element!("minecraft:mul"),
+        string_element!("argument1") => left, string_element!("argument2") => Element::FloatElement(1.0 / divisor, span)
+    ))
+}
+
+// -a => mul(a, -1)
+pub fn negate(operand: Element) -> ProcessResult {
+    match operand {
+        Element::IntElement(value, span) => ProcessResult::from_element(Element::IntElement(-value, span)),
+        Element::FloatElement(value, span) => ProcessResult::from_element(Element::FloatElement(-value, span)),
+        operand => ProcessResult::from_element(object_element!(string_element!("type") => string_element!("minecraft:mul"),
+            string_element!("argument1") => operand, string_element!("argument2") => Element::FloatElement(-1.0, Span::none())
+        )),
+    }
+}
+
+// y_clamped_gradient, checking that the y values are integers and from_y is below to_y
+pub fn y_clamped_gradient(from_y: Element, to_y: Element, from_value: Element, to_value: Element) -> ProcessResult {
+    let (from, to) = match (&from_y, &to_y) {
+        (Element::IntElement(from, _), Element::IntElement(to, _)) => (*from, *to),
+        (Element::IntElement(..), y) | (y, _) => return error(ProcessError::NonIntegerGradientY(y.clone())),
+    };
+
+    if from >= to {
+        return error(ProcessError::EmptyGradient { from_y: from, to_y: to, span: from_y.span().to(to_y.span()) });
+    }
+
+    ProcessResult::from_element(object_element!(string_element!("type") => string_element!("minecraft:y_clamped_gradient"),
+        string_element!("from_y") => from_y, string_element!("to_y") => to_y,
+        string_element!("from_value") => from_value, string_element!("to_value") => to_value
+    ))
+}
+
+// y_gradient(from_y -> from_value, to_y -> to_value) => y_clamped_gradient(from_y, to_y, from_value, to_value)
+pub fn y_gradient(from: Element, to: Element) -> ProcessResult {
+    match (split_arrow(from), split_arrow(to)) {
+        (Ok((from_y, from_value)), Ok((to_y, to_value))) => {
+            for (parameter, value) in [("from_value", &from_value), ("to_value", &to_value)] {
+                if !ArgumentType::Number.accepts(value) {
+                    return error(ProcessError::WrongArgumentType {
+                        function: String::from("y_gradient"),
+                        parameter: String::from(parameter),
+                        expected: ArgumentType::Number,
+                        got: Box::new(value.clone()),
+                    });
+                }
+            }
+
+            y_clamped_gradient(from_y, to_y, from_value, to_value)
+        },
+        (Err(element), _) | (_, Err(element)) => error(ProcessError::ExpectedArrow(element)),
+    }
+}
+
+fn error(error: ProcessError) -> ProcessResult {
+    let mut result = ProcessResult::empty();
+    result.with_error(error);
+    result
+}
+
+fn split_arrow(element: Element) -> Result<(Element, Element), Element> {
+    match element {
+        Element::BinaryElement { left, operator, right, .. } if operator.token_type() == LangTokenType::Arrow => Ok((*left, *right)),
+        element => Err(element),
+    }
+}
 
 pub fn process_operators(element: Element) -> ProcessResult {
     match element {
-        Element::BinaryElement { left, operator, right } => match operator.text() {
+        Element::BinaryElement { left, operator, right, span } => match operator.text() {
             "+" => ProcessResult::from_element(object_element!(string_element!("type") => string_element!("minecraft:add"),
                 string_element!("argument1") => *left, string_element!("argument2") => *right
             )),
-            "-" => ProcessResult::new(object_element!(string_element!("type") => string_element!("minecraft:subtract"),
-                string_element!("argument1") => *left, string_element!("argument2") => *right
-            ), Vec::new(), vec![ProcessError::UnknownOperator(String::from("-"))]),
+            "-" => subtract(*left, *right),
             "*" => ProcessResult::from_element(object_element!(string_element!("type") => string_element!("minecraft:mul"),
                 string_element!("argument1") => *left, string_element!("argument2") => *right
             )),
-            // "/" => ProcessResult::from_element(Element::IntElement(left / right)),
+            "/" => divide(*left, *right),
             _ => ProcessResult::from_element(Element::BinaryElement {
                 left,
                 operator: operator.clone(),
                 right,
+                span,
             }),
         },
+        Element::UnaryElement { operator, operand, span } => match operator.text() {
+            "-" => negate(*operand),
+            _ => ProcessResult::from_element(Element::UnaryElement { operator, operand, span }),
+        },
         _ => ProcessResult::from_element(element),
     }
 }
 
 pub fn process_functions(element: Element) -> ProcessResult {
     match element {
-        Element::FunctionCallElement { receiver: None, name, arguments: Some(mut args) } => {
+        Element::FunctionCallElement { receiver: None, name, arguments: Some(mut args), span } => {
             if args.len() == 3 {
                 todo!()
             } else if args.len() == 2 {
@@ -36,7 +140,7 @@ pub fn process_functions(element: Element) -> ProcessResult {
                         string_element!("argument1") => args.swap_remove(0), string_element!("argument2") => args.swap_remove(0)
                     ))
                 } else {
-                    ProcessResult::from_element(Element::FunctionCallElement { receiver: None, name, arguments: Some(args) })
+                    ProcessResult::from_element(Element::FunctionCallElement { receiver: None, name, arguments: Some(args), span })
                 }
             } else if args.len() == 1 {
                 if name == "interpolated" || name == "flat_cache" || name == "cache_2d" || name == "cache_once" || name == "cache_all_in_cell" || name == "abs" {
@@ -44,21 +148,116 @@ pub fn process_functions(element: Element) -> ProcessResult {
                     string_element!("argument") => args.swap_remove(0)
                 ))
                 } else {
-                    ProcessResult::from_element(Element::FunctionCallElement { receiver: None, name, arguments: Some(args) })
+                    ProcessResult::from_element(Element::FunctionCallElement { receiver: None, name, arguments: Some(args), span })
                 }
             } else {
-                ProcessResult::from_element(Element::FunctionCallElement { receiver: None, name, arguments: Some(args) })
+                ProcessResult::from_element(Element::FunctionCallElement { receiver: None, name, arguments: Some(args), span })
             }
         }
-        Element::FunctionCallElement { receiver: Some(receiver), name, arguments: Some(args) } if args.len() == 0 => {
+        Element::FunctionCallElement { receiver: Some(receiver), name, arguments: Some(args), span } if args.is_empty() => {
             if name == "interpolated" || name == "flat_cache" || name == "cache_2d" || name == "cache_once" || name == "cache_all_in_cell" || name == "abs" {
                 ProcessResult::from_element(object_element!(string_element!("type") => string_element!(String::from("minecraft:") + &name),
                     string_element!("argument") => *receiver
                 ))
             } else {
-                ProcessResult::from_element(Element::FunctionCallElement { receiver: Some(receiver), name, arguments: Some(args) })
+                ProcessResult::from_element(Element::FunctionCallElement { receiver: Some(receiver), name, arguments: Some(args), span })
             }
         }
         _ => ProcessResult::from_element(element),
     }
 }
+
+// Replaces density functions that only operate on constant numbers by the resulting number,
+// and removes additions of 0 and multiplications by 1
+pub fn fold_constants(element: Element) -> ProcessResult {
+    match fold_element(&element) {
+        Some(folded) => ProcessResult::from_element(folded),
+        None => ProcessResult::from_element(element),
+    }
+}
+
+fn fold_element(element: &Element) -> Option<Element> {
+    let name = match element.field("type") {
+        Some(Element::StringElement(name, _)) => name.strip_prefix("minecraft:")?,
+        _ => return None,
+    };
+
+    match name {
+        "add" => {
+            let (left, right) = (element.field("argument1")?, element.field("argument2")?);
+
+            fold_numbers(left, right, i32::checked_add, |a, b| a + b)
+                .or_else(|| if is_number(left, 0.0) { Some(right.clone()) } else { None })
+                .or_else(|| if is_number(right, 0.0) { Some(left.clone()) } else { None })
+        },
+        "mul" => {
+            let (left, right) = (element.field("argument1")?, element.field("argument2")?);
+
+            fold_numbers(left, right, i32::checked_mul, |a, b| a * b)
+                .or_else(|| if is_number(left, 0.0) { Some(left.clone()) } else { None })
+                .or_else(|| if is_number(right, 0.0) { Some(right.clone()) } else { None })
+                .or_else(|| if is_number(left, 1.0) { Some(right.clone()) } else { None })
+                .or_else(|| if is_number(right, 1.0) { Some(left.clone()) } else { None })
+        },
+        "min" => fold_numbers(element.field("argument1")?, element.field("argument2")?, |a, b| Some(a.min(b)), f64::min),
+        "max" => fold_numbers(element.field("argument1")?, element.field("argument2")?, |a, b| Some(a.max(b)), f64::max),
+        "clamp" => {
+            let input = element.field("input")?;
+            let min = element.field("min")?;
+            let max = element.field("max")?;
+
+            match (input, min, max) {
+                (Element::IntElement(input, _), Element::IntElement(min, _), Element::IntElement(max, _)) if min <= max =>
+                    Some(Element::IntElement(*input.clamp(min, max), Span::none())),
+                _ => {
+                    let (input, min, max) = (input.as_number()?, min.as_number()?, max.as_number()?);
+
+                    if min <= max {
+                        Some(Element::FloatElement(input.clamp(min, max), Span::none()))
+                    } else {
+                        None
+                    }
+                },
+            }
+        },
+        "abs" => fold_number(element.field("argument")?, i32::checked_abs, f64::abs),
+        "square" => fold_number(element.field("argument")?, |a| a.checked_mul(a), |a| a * a),
+        "cube" => fold_number(element.field("argument")?, |a| a.checked_mul(a)?.checked_mul(a), |a| a * a * a),
+        "half_negative" => fold_number(element.field("argument")?,
+            |a| if a < 0 { None } else { Some(a) }, |a| if a < 0.0 { a * 0.5 } else { a }),
+        "quarter_negative" => fold_number(element.field("argument")?,
+            |a| if a < 0 { None } else { Some(a) }, |a| if a < 0.0 { a * 0.25 } else { a }),
+        "squeeze" => fold_number(element.field("argument")?, |_| None, |a| {
+            let a = a.clamp(-1.0, 1.0);
+            a / 2.0 - a * a * a / 24.0
+        }),
+        // Caching a constant does nothing
+        "interpolated" | "flat_cache" | "cache_2d" | "cache_once" | "cache_all_in_cell" => match element.field("argument")? {
+            argument @ (Element::IntElement(..) | Element::FloatElement(..)) => Some(argument.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_number(element: &Element, number: f64) -> bool {
+    element.as_number() == Some(number)
+}
+
+// The int operation returns None if the result can't be represented as an int
+fn fold_number(element: &Element, int_op: impl Fn(i32) -> Option<i32>, float_op: impl Fn(f64) -> f64) -> Option<Element> {
+    match element {
+        Element::IntElement(value, _) => Some(int_op(*value).map(|value| Element::IntElement(value, Span::none()))
+            .unwrap_or_else(|| Element::FloatElement(float_op(*value as f64), Span::none()))),
+        Element::FloatElement(value, _) => Some(Element::FloatElement(float_op(*value), Span::none())),
+        _ => None,
+    }
+}
+
+fn fold_numbers(left: &Element, right: &Element, int_op: impl Fn(i32, i32) -> Option<i32>, float_op: impl Fn(f64, f64) -> f64) -> Option<Element> {
+    match (left, right) {
+        (Element::IntElement(left, _), Element::IntElement(right, _)) => Some(int_op(*left, *right).map(|value| Element::IntElement(value, Span::none()))
+            .unwrap_or_else(|| Element::FloatElement(float_op(*left as f64, *right as f64), Span::none()))),
+        _ => Some(Element::FloatElement(float_op(left.as_number()?, right.as_number()?), Span::none())),
+    }
+}
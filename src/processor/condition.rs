@@ -0,0 +1,85 @@
+use crate::element::Element;
+use crate::parser::Span;
+use crate::parser::lexer::LangTokenType;
+use crate::processor::ProcessError;
+
+// Bounds used by range_choice for a side of the range that is not given
+pub const OPEN_MIN: f64 = -1000000.0;
+pub const OPEN_MAX: f64 = 1000000.0;
+
+// Lowers `if condition then when_true else when_false` to the arguments of range_choice
+// (input, min_inclusive, max_exclusive, when_in_range, when_out_of_range)
+pub fn range_choice_arguments(condition: Element, when_true: Element, when_false: Element) -> Result<Vec<Element>, ProcessError> {
+    let (input, min, max) = range_bounds(condition)?;
+
+    Ok(vec![
+        input,
+        min.unwrap_or(Element::FloatElement(OPEN_MIN, Span::none())),
+        max.unwrap_or(Element::FloatElement(OPEN_MAX, Span::none())),
+        when_true,
+        when_false,
+    ])
+}
+
+// Splits a condition like `min <= input < max` into the input and the bounds
+fn range_bounds(condition: Element) -> Result<(Element, Option<Element>, Option<Element>), ProcessError> {
+    let mut operands = Vec::new();
+    let mut operators = Vec::new();
+    flatten_comparison(condition.clone(), &mut operands, &mut operators);
+
+    // `max > input >= min` is the same as `min <= input < max`
+    if !operators.is_empty() && operators.iter().all(|operator| matches!(operator, LangTokenType::Greater | LangTokenType::GreaterEqual)) {
+        operands.reverse();
+        operators.reverse();
+
+        for operator in operators.iter_mut() {
+            *operator = if *operator == LangTokenType::Greater { LangTokenType::Less } else { LangTokenType::LessEqual };
+        }
+    }
+
+    // With one comparison, the bound is the constant side
+    let input_first = operands.len() == 2 && operands[0].as_number().is_none()
+        && (operands[1].as_number().is_some() || operators[0] == LangTokenType::Less);
+
+    let (input, min, max) = match (operators.as_slice(), operands.len()) {
+        ([LangTokenType::LessEqual, LangTokenType::Less], 3) => {
+            let max = operands.pop();
+            let input = operands.pop().unwrap();
+            (input, operands.pop(), max)
+        },
+        ([LangTokenType::LessEqual], 2) if !input_first => {
+            let input = operands.pop().unwrap();
+            (input, operands.pop(), None)
+        },
+        ([LangTokenType::Less], 2) if input_first => {
+            let max = operands.pop();
+            (operands.pop().unwrap(), None, max)
+        },
+        // range_choice includes the minimum and excludes the maximum
+        ([LangTokenType::Less | LangTokenType::LessEqual], 2) | ([LangTokenType::Less | LangTokenType::LessEqual, LangTokenType::Less | LangTokenType::LessEqual], 3) =>
+            return Err(ProcessError::UnsupportedBound(condition)),
+        _ => return Err(ProcessError::UnsupportedCondition(condition)),
+    };
+
+    // range_choice only takes constant bounds
+    if min.iter().chain(max.iter()).any(|bound| bound.as_number().is_none()) {
+        return Err(ProcessError::UnsupportedCondition(condition));
+    }
+
+    Ok((input, min, max))
+}
+
+fn flatten_comparison(element: Element, operands: &mut Vec<Element>, operators: &mut Vec<LangTokenType>) {
+    match element {
+        Element::BinaryElement { left, operator, right, .. } if is_comparison(operator.token_type()) => {
+            flatten_comparison(*left, operands, operators);
+            operators.push(operator.token_type());
+            operands.push(*right);
+        },
+        element => operands.push(element),
+    }
+}
+
+fn is_comparison(token_type: LangTokenType) -> bool {
+    matches!(token_type, LangTokenType::Less | LangTokenType::LessEqual | LangTokenType::Greater | LangTokenType::GreaterEqual)
+}
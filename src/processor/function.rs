@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter};
+
+use crate::element::Element;
+use crate::parser::Span;
+use crate::processor::{ProcessError, ProcessResult};
+use crate::util;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgumentType {
+    DensityFunction,
+    Number,
+    Int,
+    String,
+    NoiseId, // A string that is a resource location
+    OneOf(&'static [&'static str]),
+    Any,
+}
+
+impl ArgumentType {
+    // Arguments are checked before they are processed, so operators and function calls
+    // are accepted for numbers, as they can still be folded into one
+    pub fn accepts(&self, element: &Element) -> bool {
+        if let Element::ErrorElement(_) = element {
+            return true; // The syntax error was already reported
+        }
+
+        match self {
+            ArgumentType::DensityFunction => !matches!(element, Element::NullElement(_) | Element::BooleanElement(..) | Element::ArrayElement(..)),
+            ArgumentType::Number => matches!(element, Element::IntElement(..) | Element::FloatElement(..)
+                | Element::UnaryElement { .. } | Element::BinaryElement { .. } | Element::FunctionCallElement { .. }),
+            ArgumentType::Int => matches!(element, Element::IntElement(..)
+                | Element::UnaryElement { .. } | Element::BinaryElement { .. } | Element::FunctionCallElement { .. }),
+            ArgumentType::String => matches!(element, Element::StringElement(..)),
+            ArgumentType::NoiseId => matches!(element, Element::StringElement(id, _) if util::is_resource_location(id)),
+            ArgumentType::OneOf(values) => matches!(element, Element::StringElement(value, _) if values.contains(&value.as_str())),
+            ArgumentType::Any => true,
+        }
+    }
+
+    // Checks numbers again once the argument is processed and folded, when density functions
+    // are no longer accepted. Operators and function calls that are left are unresolved, which
+    // is reported separately.
+    pub fn accepts_processed(&self, element: &Element) -> bool {
+        match self {
+            ArgumentType::Number => matches!(element, Element::IntElement(..) | Element::FloatElement(..)
+                | Element::UnaryElement { .. } | Element::BinaryElement { .. } | Element::FunctionCallElement { .. }
+                | Element::NameElement(..) | Element::ErrorElement(_)),
+            ArgumentType::Int => matches!(element, Element::IntElement(..)
+                | Element::UnaryElement { .. } | Element::BinaryElement { .. } | Element::FunctionCallElement { .. }
+                | Element::NameElement(..) | Element::ErrorElement(_)),
+            _ => self.accepts(element),
+        }
+    }
+}
+
+impl Display for ArgumentType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgumentType::DensityFunction => f.write_str("a density function"),
+            ArgumentType::Number => f.write_str("a number"),
+            ArgumentType::Int => f.write_str("an integer"),
+            ArgumentType::String => f.write_str("a string"),
+            ArgumentType::NoiseId => f.write_str("a noise id"),
+            ArgumentType::OneOf(values) => write!(f, "one of {}", values.iter()
+                .map(|value| format!("\"{}\"", value)).collect::<Vec<_>>().join(", ")),
+            ArgumentType::Any => f.write_str("any value"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameter {
+    key: Cow<'static, str>,
+    argument_type: ArgumentType,
+    default: Option<Element>,
+}
+
+impl Parameter {
+    pub fn new_with_config(key: String, argument_type: ArgumentType, default: Option<Element>) -> Parameter {
+        Parameter {
+            key: Cow::Owned(key), argument_type, default,
+        }
+    }
+
+    pub fn new(key: &'static str, argument_type: ArgumentType) -> Parameter {
+        Parameter {
+            key: Cow::Borrowed(key), argument_type, default: None,
+        }
+    }
+
+    pub fn optional(key: &'static str, argument_type: ArgumentType, default: Element) -> Parameter {
+        Parameter {
+            key: Cow::Borrowed(key), argument_type, default: Some(default),
+        }
+    }
+
+    pub fn density_function(key: &'static str) -> Parameter {
+        Parameter::new(key, ArgumentType::DensityFunction)
+    }
+
+    pub fn key(&self) -> &str { &self.key }
+    pub fn argument_type(&self) -> ArgumentType { self.argument_type }
+    pub fn default(&self) -> Option<&Element> { self.default.as_ref() }
+}
+
+// A function that is turned into an object with a "type" field and one field for every argument,
+// or, if it has a custom function, into whatever that returns
+pub struct Function {
+    pub name: String,
+    namespace: Cow<'static, str>,
+    parameters: Vec<Parameter>,
+    pub allow_method_syntax: bool, // The receiver is used as the first argument
+    pub allow_as_symbol: bool, // Can be used without parentheses if there are no required parameters
+    custom: Option<Box<dyn Fn(Vec<Element>) -> ProcessResult + Send + Sync>>,
+}
+
+impl Function {
+    pub fn new_with_config(name: String, namespace: String, parameters: Vec<Parameter>, allow_method_syntax: bool, allow_as_symbol: bool) -> Function {
+        Function {
+            name, namespace: Cow::Owned(namespace), parameters, allow_method_syntax, allow_as_symbol, custom: None,
+        }
+    }
+
+    pub fn new(name: String, parameters: Vec<Parameter>) -> Function {
+        Function {
+            name, namespace: Cow::Borrowed("minecraft"), parameters, allow_method_syntax: false, allow_as_symbol: false, custom: None,
+        }
+    }
+
+    pub fn new_with_method_syntax(name: String, parameters: Vec<Parameter>) -> Function {
+        Function {
+            allow_method_syntax: true,
+            ..Function::new(name, parameters)
+        }
+    }
+
+    // The custom function gets one argument for every parameter, with defaults already filled in
+    pub fn new_custom(name: String, parameters: Vec<Parameter>, allow_method_syntax: bool, function: Box<dyn Fn(Vec<Element>) -> ProcessResult + Send + Sync>) -> Function {
+        Function {
+            allow_method_syntax,
+            custom: Some(function),
+            ..Function::new(name, parameters)
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn namespace(&self) -> &str { &self.namespace }
+    pub fn parameters(&self) -> &[Parameter] { &self.parameters }
+    pub fn allow_method_syntax(&self) -> bool { self.allow_method_syntax }
+    pub fn allow_as_symbol(&self) -> bool { self.allow_as_symbol }
+    pub fn custom(&self) -> Option<&(dyn Fn(Vec<Element>) -> ProcessResult + Send + Sync)> { self.custom.as_deref() }
+
+    pub fn type_name(&self) -> String {
+        format!("{}:{}", self.namespace, self.name)
+    }
+
+    pub fn min_arguments(&self) -> usize {
+        self.parameters.iter().filter(|parameter| parameter.default().is_none()).count()
+    }
+
+    pub fn max_arguments(&self) -> usize {
+        self.parameters.len()
+    }
+
+    // Matches positional and named arguments to the parameters, and fills in the defaults for missing ones.
+    // `method` is only used to not count the receiver in error messages, `span` is the span of the call.
+    pub fn bind_arguments(&self, arguments: Vec<Element>, method: bool, span: Span) -> Result<Vec<Element>, ProcessError> {
+        let mut bound: Vec<Option<Element>> = vec![None; self.parameters.len()];
+        let mut positional_count = 0;
+        let mut named = false;
+
+        for argument in arguments {
+            match argument {
+                Element::NamedArgumentElement { name, value, span } => {
+                    named = true;
+
+                    let index = self.parameters.iter().position(|parameter| parameter.key() == name)
+                        .ok_or_else(|| ProcessError::UnknownArgument { function: self.name.clone(), name: name.clone(), span })?;
+
+                    if bound[index].is_some() {
+                        return Err(ProcessError::DuplicateArgument { function: self.name.clone(), name, span });
+                    }
+
+                    bound[index] = Some(*value);
+                },
+                argument => {
+                    if named {
+                        return Err(ProcessError::PositionalAfterNamedArgument(self.name.clone(), argument.span()));
+                    }
+
+                    if positional_count < bound.len() {
+                        bound[positional_count] = Some(argument);
+                    }
+
+                    positional_count += 1;
+                },
+            }
+        }
+
+        let receiver = if method { 1 } else { 0 };
+
+        if positional_count > self.max_arguments() || (!named && positional_count < self.min_arguments()) {
+            return Err(ProcessError::WrongArgumentCount {
+                name: self.name.clone(),
+                min: self.min_arguments().saturating_sub(receiver),
+                max: self.max_arguments().saturating_sub(receiver),
+                got: positional_count.saturating_sub(receiver),
+                span,
+            });
+        }
+
+        self.parameters.iter().zip(bound).map(|(parameter, argument)| argument
+            .or_else(|| parameter.default().cloned())
+            .ok_or_else(|| ProcessError::MissingArgument { function: self.name.clone(), name: parameter.key().to_owned(), span })
+        ).collect()
+    }
+}
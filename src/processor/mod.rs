@@ -1,182 +1,105 @@
 mod result;
+mod scope;
+mod function;
+mod condition;
+mod spline;
+pub mod registry;
 
 use std::borrow::Cow;
+
 pub use result::*;
+pub use scope::*;
+pub use function::*;
+pub use condition::{OPEN_MAX, OPEN_MIN};
 
 use crate::element::Element;
+use crate::module::{Declaration, Module};
+use crate::parser::Span;
+use crate::parser::lexer::LangToken;
 use crate::{string_element, object_element};
 
-pub struct NoArgFunction {
-    pub name: String,
-    namespace: Cow<'static, str>,
-    pub allow_as_symbol: bool,
-}
-
-impl NoArgFunction {
-    pub fn new_with_config(name: String, namespace: String, allow_as_symbol: bool) -> NoArgFunction {
-        NoArgFunction {
-            name, namespace: Cow::Owned(namespace), allow_as_symbol,
-        }
-    }
-
-    pub fn new_with_allow_as_symbol(name: String, allow_as_symbol: bool) -> NoArgFunction {
-        NoArgFunction {
-            name, namespace: Cow::Borrowed("minecraft"), allow_as_symbol,
-        }
-    }
-
-    pub fn new(name: String) -> NoArgFunction {
-        NoArgFunction::new_with_allow_as_symbol(name, false)
-    }
-
-    pub fn name(&self) -> &str { &self.name }
-    pub fn namespace(&self) -> &str { &self.namespace }
-    pub fn allow_as_symbol(&self) -> bool { self.allow_as_symbol }
-}
-
-pub struct OneArgFunction {
-    pub name: String,
-    namespace: Cow<'static, str>,
-    argument_key: Cow<'static, str>,
-    pub allow_method_syntax: bool,
-}
-
-impl OneArgFunction {
-    pub fn new_with_config(name: String, namespace: String, argument_key: String, allow_method_syntax: bool) -> OneArgFunction {
-        OneArgFunction {
-            name,
-            namespace: Cow::Owned(namespace),
-            argument_key: Cow::Owned(argument_key),
-            allow_method_syntax,
-        }
-    }
-
-    pub fn new(name: String, allow_method_syntax: bool) -> OneArgFunction {
-        OneArgFunction {
-            name,
-            namespace: Cow::Borrowed("minecraft"),
-            argument_key: Cow::Borrowed("argument"),
-            allow_method_syntax,
-        }
-    }
-
-    pub fn new_with_method_syntax(name: String) -> OneArgFunction {
-        OneArgFunction::new(name, true)
-    }
-
-    pub fn name(&self) -> &str { &self.name }
-    pub fn namespace(&self) -> &str { &self.namespace }
-    pub fn argument_key(&self) -> &str { &self.argument_key }
-    pub fn allow_method_syntax(&self) -> bool { self.allow_method_syntax }
-}
-
-pub struct TwoArgsFunction {
+pub struct BinaryOperator {
     pub name: String,
     namespace: Cow<'static, str>,
+    internal_name: String,
     argument1_key: Cow<'static, str>,
     argument2_key: Cow<'static, str>,
-    pub allow_method_syntax: bool,
 }
 
-impl TwoArgsFunction {
-    pub fn new_with_config(name: String, namespace: String, argument1_key: String, argument2_key: String, allow_method_syntax: bool) -> TwoArgsFunction {
-        TwoArgsFunction {
+impl BinaryOperator {
+    pub fn new_with_config(name: String, namespace: String, internal_name: String, argument1_key: String, argument2_key: String) -> BinaryOperator {
+        BinaryOperator {
             name,
             namespace: Cow::Owned(namespace),
+            internal_name,
             argument1_key: Cow::Owned(argument1_key),
             argument2_key: Cow::Owned(argument2_key),
-            allow_method_syntax,
         }
     }
 
-    pub fn new(name: String, allow_method_syntax: bool) -> TwoArgsFunction {
-        TwoArgsFunction {
+    pub fn new(name: String, internal_name: String) -> BinaryOperator {
+        BinaryOperator {
             name,
             namespace: Cow::Borrowed("minecraft"),
+            internal_name,
             argument1_key: Cow::Borrowed("argument1"),
             argument2_key: Cow::Borrowed("argument2"),
-            allow_method_syntax,
         }
     }
 
-    pub fn new_without_method_syntax(name: String) -> TwoArgsFunction {
-        TwoArgsFunction::new(name, false) // Functions with two arguments can't be used as methods by default
-    }
-
     pub fn name(&self) -> &str { &self.name }
     pub fn namespace(&self) -> &str { &self.namespace }
+    pub fn internal_name(&self) -> &str { &self.internal_name }
     pub fn argument1_key(&self) -> &str { &self.argument1_key }
     pub fn argument2_key(&self) -> &str { &self.argument2_key }
-    pub fn allow_method_syntax(&self) -> bool { self.allow_method_syntax }
 }
 
-pub struct CustomThreeArgsFunction {
+pub struct CustomBinaryOperator {
     pub name: String,
-    pub allow_method_syntax: bool,
-    function: Box<dyn Fn(Element, Element, Element) -> ProcessResult>,
+    function: Box<dyn Fn(Element, Element) -> ProcessResult + Send + Sync>,
 }
 
-impl CustomThreeArgsFunction {
-    pub fn new_with_allow_method_syntax(name: String, allow_method_syntax: bool, function: Box<dyn Fn(Element, Element, Element) -> ProcessResult>) -> CustomThreeArgsFunction {
-        CustomThreeArgsFunction {
-            name, allow_method_syntax, function
+impl CustomBinaryOperator {
+    pub fn new(name: String, function: Box<dyn Fn(Element, Element) -> ProcessResult + Send + Sync>) -> CustomBinaryOperator {
+        CustomBinaryOperator {
+            name, function
         }
     }
 
-    pub fn new(name: String, function: Box<dyn Fn(Element, Element, Element) -> ProcessResult>) -> CustomThreeArgsFunction {
-        CustomThreeArgsFunction::new_with_allow_method_syntax(name, false, function)
-    }
-
     pub fn name(&self) -> &str { &self.name }
-    pub fn allow_method_syntax(&self) -> bool { self.allow_method_syntax }
-    pub fn function(&self) -> &Box<dyn Fn(Element, Element, Element) -> ProcessResult> { &self.function }
+    pub fn function(&self) -> &dyn Fn(Element, Element) -> ProcessResult { &self.function }
 }
 
-pub struct BinaryOperator {
+pub struct CustomUnaryOperator {
     pub name: String,
-    namespace: Cow<'static, str>,
-    internal_name: String,
-    argument1_key: Cow<'static, str>,
-    argument2_key: Cow<'static, str>,
+    function: Box<dyn Fn(Element) -> ProcessResult + Send + Sync>,
 }
 
-impl BinaryOperator {
-    pub fn new_with_config(name: String, namespace: String, internal_name: String, argument1_key: String, argument2_key: String) -> BinaryOperator {
-        BinaryOperator {
-            name,
-            namespace: Cow::Owned(namespace),
-            internal_name,
-            argument1_key: Cow::Owned(argument1_key),
-            argument2_key: Cow::Owned(argument2_key),
-        }
-    }
-
-    pub fn new(name: String, internal_name: String) -> BinaryOperator {
-        BinaryOperator {
-            name,
-            namespace: Cow::Borrowed("minecraft"),
-            internal_name,
-            argument1_key: Cow::Borrowed("argument1"),
-            argument2_key: Cow::Borrowed("argument2"),
+impl CustomUnaryOperator {
+    pub fn new(name: String, function: Box<dyn Fn(Element) -> ProcessResult + Send + Sync>) -> CustomUnaryOperator {
+        CustomUnaryOperator {
+            name, function
         }
     }
 
     pub fn name(&self) -> &str { &self.name }
-    pub fn namespace(&self) -> &str { &self.namespace }
-    pub fn internal_name(&self) -> &str { &self.internal_name }
-    pub fn argument1_key(&self) -> &str { &self.argument1_key }
-    pub fn argument2_key(&self) -> &str { &self.argument2_key }
+    pub fn function(&self) -> &dyn Fn(Element) -> ProcessResult { &self.function }
 }
 
 pub struct ElementProcessor<'a> {
-    preprocessors: Vec<Box<dyn Fn(Element) -> ProcessResult + 'a>>,
-    postprocessors: Vec<Box<dyn Fn(Element) -> ProcessResult + 'a>>,
+    preprocessors: Vec<Box<dyn Fn(Element) -> ProcessResult + Send + Sync + 'a>>,
+    postprocessors: Vec<Box<dyn Fn(Element) -> ProcessResult + Send + Sync + 'a>>,
 
-    no_arg_functions: Vec<NoArgFunction>,
-    one_arg_functions: Vec<OneArgFunction>,
-    two_args_functions: Vec<TwoArgsFunction>,
-    custom_three_args_functions: Vec<CustomThreeArgsFunction>,
+    functions: Vec<Function>,
     binary_operators: Vec<BinaryOperator>, // Will ignore allow_method_syntax
+    custom_binary_operators: Vec<CustomBinaryOperator>,
+    custom_unary_operators: Vec<CustomUnaryOperator>,
+}
+
+impl<'a> Default for ElementProcessor<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<'a> ElementProcessor<'a> {
@@ -185,245 +108,443 @@ impl<'a> ElementProcessor<'a> {
             preprocessors: Vec::new(),
             postprocessors: Vec::new(),
 
-            no_arg_functions: Vec::new(), one_arg_functions: Vec::new(), two_args_functions: Vec::new(),
-            custom_three_args_functions: Vec::new(),
+            functions: Vec::new(),
             binary_operators: Vec::new(),
+            custom_binary_operators: Vec::new(), custom_unary_operators: Vec::new(),
         }
     }
 
-    pub fn add_preprocessor<F: 'a>(&mut self, preprocessor: F) where F: Fn(Element) -> ProcessResult {
+    pub fn add_preprocessor<F>(&mut self, preprocessor: F) where F: Fn(Element) -> ProcessResult + Send + Sync + 'a {
         self.preprocessors.push(Box::new(preprocessor));
     }
 
-    pub fn add_postprocessor<F: 'a>(&mut self, postprocessor: F) where F: Fn(Element) -> ProcessResult {
+    pub fn add_postprocessor<F>(&mut self, postprocessor: F) where F: Fn(Element) -> ProcessResult + Send + Sync + 'a {
         self.postprocessors.push(Box::new(postprocessor));
     }
 
-    pub fn add_no_arg_function(&mut self, function: NoArgFunction) {
-        self.no_arg_functions.push(function);
+    pub fn add_function(&mut self, function: Function) {
+        self.functions.push(function);
     }
 
-    pub fn add_one_arg_function(&mut self, function: OneArgFunction) {
-        self.one_arg_functions.push(function);
+    pub fn add_binary_operator(&mut self, function: BinaryOperator) {
+        self.binary_operators.push(function);
     }
 
-    pub fn add_two_args_function(&mut self, function: TwoArgsFunction) {
-        self.two_args_functions.push(function);
+    pub fn add_custom_binary_operator(&mut self, function: CustomBinaryOperator) {
+        self.custom_binary_operators.push(function);
     }
 
-    pub fn add_custom_three_args_function(&mut self, function: CustomThreeArgsFunction) {
-        self.custom_three_args_functions.push(function);
+    pub fn add_custom_unary_operator(&mut self, function: CustomUnaryOperator) {
+        self.custom_unary_operators.push(function);
     }
 
-    pub fn add_binary_operator(&mut self, function: BinaryOperator) {
-        self.binary_operators.push(function);
+    pub fn functions(&self) -> &[Function] { &self.functions }
+    pub fn binary_operators(&self) -> &[BinaryOperator] { &self.binary_operators }
+
+    pub fn process_module(&self, module: Module) -> ProcessResult {
+        self.process_module_with_imports(Vec::new(), module)
+    }
+
+    // Imported modules have to be in the order their declarations depend on each other
+    pub fn process_module_with_imports(&self, imports: Vec<Module>, module: Module) -> ProcessResult {
+        let mut scope = Scope::new();
+        let mut result = ProcessResult::empty();
+
+        for import in imports {
+            ElementProcessor::declare_all(&mut scope, import.declarations, &mut result);
+        }
+
+        ElementProcessor::declare_all(&mut scope, module.declarations, &mut result);
+
+        if let Some(element) = module.element {
+            let mut element_result = scope.resolve(element);
+            result.append_warnings_and_errors(&mut element_result);
+
+            result = result.with_element(element_result.take_element())
+                .flat_map(|element| self.process(element));
+        }
+
+        // Errors about parts that failed to parse were already reported as syntax errors
+        result.errors.retain(|error| !error.involves_error_element());
+        result
+    }
+
+    fn declare_all(scope: &mut Scope, declarations: Vec<Declaration>, result: &mut ProcessResult) {
+        for declaration in declarations {
+            match declaration {
+                Declaration::Import { .. } => {}, // Already loaded
+                Declaration::Constant { name, value } => {
+                    let mut value_result = scope.resolve(value);
+                    result.append_warnings_and_errors(&mut value_result);
+
+                    if let Some(value) = value_result.take_element() {
+                        if let Err(error) = scope.define_constant(name, value) {
+                            result.with_error(error);
+                        }
+                    }
+                },
+                Declaration::Function { name, parameters, body } => {
+                    if let Err(error) = scope.define_function(MacroFunction::new(name, parameters, body)) {
+                        result.with_error(error);
+                    }
+                },
+            }
+        }
     }
 
     pub fn process(&self, element: Element) -> ProcessResult {
+        // Elements created by lowering and by the postprocessors take the span of the element they replace
+        let span = element.span();
+
         // let mut pre_result = ElementProcessor::apply_processors(element, &self.preprocessors);
-        let mut result = self.pre_process(element);
+        let mut result = self.pre_process(element).map(|element| element.inherit_span(span));
 
         if let Some(element) = result.take_element() {
-            let result = match element {
-                Element::ArrayElement(values) => {
-                    let mut result_values = Vec::new();
+            // Each kind of element is processed in a function of its own, which keeps the stack
+            // small for deeply nested elements
+            let mut sub_result = match element {
+                Element::ArrayElement(values, span) => self.process_array(values, span),
+                Element::ObjectElement(fields, span) => self.process_object(fields, span),
+                Element::NamedArgumentElement { name, value, span } => self.process_named_argument(name, *value, span),
+                Element::UnaryElement { operator, operand, span } => self.process_unary(operator, *operand, span),
+                Element::BinaryElement { left, operator, right, span } => self.process_binary(*left, operator, *right, span),
+                Element::FunctionCallElement { receiver, name, arguments, span } => self.process_call(receiver, name, arguments, span),
+                _ => ProcessResult::from_element(element),
+            };
+            result.append_warnings_and_errors(&mut sub_result);
 
-                    for mut sub_result in values.into_iter().map(|element| self.process(element)) {
-                        result.append_warnings_and_errors(&mut sub_result);
+            result.with_element(sub_result.take_element())
+                .flat_map(|element| ElementProcessor::apply_processors(element, &self.postprocessors))
+                .map(|element| element.inherit_span(span))
+                .flat_map(|element| self.check_numbers(element))
+        } else {
+            result
+        }
+    }
 
-                        if let None = sub_result.element() {
-                            return result;
-                        }
+    fn process_array(&self, values: Vec<Element>, span: Span) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut result_values = Vec::new();
+        let mut failed = false; // The other values are still processed to report their errors
 
-                        result_values.push(sub_result.take_element().unwrap());
-                    }
+        for mut sub_result in values.into_iter().map(|element| self.process(element)) {
+            result.append_warnings_and_errors(&mut sub_result);
 
-                    result.with_element(Some(Element::ArrayElement(result_values)))
-                }
-                Element::ObjectElement(fields) => {
-                    let mut result_fields = Vec::new();
+            match sub_result.take_element() {
+                Some(element) => result_values.push(element),
+                None => failed = true,
+            }
+        }
 
-                    for (key, value) in fields {
-                        let mut key_result = self.process(key);
-                        let mut value_result = self.process(value);
+        if failed {
+            return result;
+        }
 
-                        result.append_warnings_and_errors(&mut key_result);
-                        result.append_warnings_and_errors(&mut value_result);
+        result.with_element(Some(Element::ArrayElement(result_values, span)))
+    }
 
-                        if key_result.element().is_none() || value_result.element().is_none() {
-                            return result;
-                        }
+    fn process_object(&self, fields: Vec<(Element, Element)>, span: Span) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut result_fields = Vec::new();
+        let mut failed = false;
 
-                        result_fields.push((key_result.take_element().unwrap(), value_result.take_element().unwrap()));
-                    }
+        for (key, value) in fields {
+            let mut key_result = self.process(key);
+            let mut value_result = self.process(value);
 
-                    result.with_element(Some(Element::ObjectElement(result_fields)))
-                }
-                Element::BinaryElement { left, operator, right } => {
-                    let mut left_result = self.process(*left);
-                    let mut right_result = self.process(*right);
+            result.append_warnings_and_errors(&mut key_result);
+            result.append_warnings_and_errors(&mut value_result);
 
-                    result.append_warnings_and_errors(&mut left_result);
-                    result.append_warnings_and_errors(&mut right_result);
+            match (key_result.take_element(), value_result.take_element()) {
+                (Some(key), Some(value)) => result_fields.push((key, value)),
+                _ => failed = true,
+            }
+        }
 
-                    if left_result.element().is_none() || right_result.element().is_none() {
-                        return result;
-                    }
+        if failed {
+            return result;
+        }
 
-                    result.with_element(Some(Element::BinaryElement {
-                        left: Box::new(left_result.take_element().unwrap()),
-                        operator,
-                        right: Box::new(right_result.take_element().unwrap()),
-                    }))
-                }
-                Element::FunctionCallElement { receiver, name, arguments } => {
-                    let mut receiver_result = if let Some(receiver) = receiver {
-                        let mut receiver_result = self.process(*receiver);
-                        result.append_warnings_and_errors(&mut receiver_result);
-                        receiver_result
-                    } else {
-                        ProcessResult::empty()
-                    };
-
-                    let arguments_result = if let Some(arguments) = arguments {
-                        let mut result_arguments = Vec::new();
-
-                        for mut argument_result in arguments.into_iter().map(|element| self.process(element)) {
-                            result.append_warnings_and_errors(&mut argument_result);
-
-                            if let None = argument_result.element() {
-                                return result;
-                            }
-
-                            result_arguments.push(argument_result.take_element().unwrap());
-                        }
+        result.with_element(Some(Element::ObjectElement(result_fields, span)))
+    }
+
+    fn process_named_argument(&self, name: String, value: Element, span: Span) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut value_result = self.process(value);
+        result.append_warnings_and_errors(&mut value_result);
+
+        if value_result.element().is_none() {
+            return result;
+        }
+
+        result.with_element(Some(Element::NamedArgumentElement {
+            name,
+            value: Box::new(value_result.take_element().unwrap()),
+            span,
+        }))
+    }
 
-                        Some(result_arguments)
-                    } else {
-                        None
-                    };
+    fn process_unary(&self, operator: LangToken, operand: Element, span: Span) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut operand_result = self.process(operand);
+        result.append_warnings_and_errors(&mut operand_result);
 
-                    result.with_element(Some(Element::FunctionCallElement {
-                        receiver: receiver_result.take_element().map(Box::new),
-                        name,
-                        arguments: arguments_result,
-                    }))
+        if operand_result.element().is_none() {
+            return result;
+        }
+
+        result.with_element(Some(Element::UnaryElement {
+            operator,
+            operand: Box::new(operand_result.take_element().unwrap()),
+            span,
+        }))
+    }
+
+    fn process_binary(&self, left: Element, operator: LangToken, right: Element, span: Span) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut left_result = self.process(left);
+        let mut right_result = self.process(right);
+
+        result.append_warnings_and_errors(&mut left_result);
+        result.append_warnings_and_errors(&mut right_result);
+
+        if left_result.element().is_none() || right_result.element().is_none() {
+            return result;
+        }
+
+        result.with_element(Some(Element::BinaryElement {
+            left: Box::new(left_result.take_element().unwrap()),
+            operator,
+            right: Box::new(right_result.take_element().unwrap()),
+            span,
+        }))
+    }
+
+    fn process_call(&self, receiver: Option<Box<Element>>, name: String, arguments: Option<Vec<Element>>, span: Span) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut receiver_result = if let Some(receiver) = receiver {
+            let mut receiver_result = self.process(*receiver);
+            result.append_warnings_and_errors(&mut receiver_result);
+            receiver_result
+        } else {
+            ProcessResult::empty()
+        };
+
+        let arguments_result = if let Some(arguments) = arguments {
+            let mut result_arguments = Vec::new();
+
+            for mut argument_result in arguments.into_iter().map(|element| self.process(element)) {
+                result.append_warnings_and_errors(&mut argument_result);
+
+                if argument_result.element().is_none() {
+                    return result;
                 }
 
-                _ => ProcessResult::from_element(element),
-            };
+                result_arguments.push(argument_result.take_element().unwrap());
+            }
 
-            result.flat_map(|element| ElementProcessor::apply_processors(element, &self.postprocessors))
+            Some(result_arguments)
         } else {
-            result
-        }
+            None
+        };
+
+        result.with_element(Some(Element::FunctionCallElement {
+            receiver: receiver_result.take_element().map(Box::new),
+            name,
+            arguments: arguments_result,
+            span,
+        }))
     }
 
     fn pre_process(&self, element: Element) -> ProcessResult {
         match element {
-            Element::NameElement(name) => {
-                for function in &self.no_arg_functions {
+            Element::NameElement(name, span) => {
+                for function in &self.functions {
                     if function.allow_as_symbol() && function.name() == name {
-                        return ProcessResult::from_element(object_element!(
-                                string_element!("type") => Element::StringElement(format!("{}:{}", function.namespace(), function.name()))
-                        ));
+                        if let Ok(arguments) = function.bind_arguments(Vec::new(), false, span) {
+                            return self.call_function(function, arguments);
+                        }
                     }
                 }
 
-                ProcessResult::from_element(Element::NameElement(name))
+                ProcessResult::from_element(Element::NameElement(name, span))
             },
-            Element::BinaryElement { left, operator, right } => {
+            Element::BinaryElement { left, operator, right, span } => {
                 for function in &self.binary_operators {
                     if function.name() == operator.text() {
                         return ProcessResult::from_element(object_element!(
-                            string_element!("type") => Element::StringElement(format!("{}:{}", function.namespace(), function.internal_name())),
-                            Element::StringElement(function.argument1_key().to_owned()) => *left,
-                            Element::StringElement(function.argument2_key().to_owned()) => *right
+                            string_element!("type") => string_element!(format!("{}:{}", function.namespace(), function.internal_name())),
+                            string_element!(function.argument1_key().to_owned()) => *left,
+                            string_element!(function.argument2_key().to_owned()) => *right
                         ));
                     }
                 }
 
-                ProcessResult::from_element(Element::BinaryElement { left, operator, right })
-            }
-            Element::FunctionCallElement { receiver: Some(receiver), name, arguments: Some(mut args) } => {
-                if args.len() == 0 {
-                    for function in &self.one_arg_functions {
-                        if function.allow_method_syntax() && function.name() == name {
-                            return ProcessResult::from_element(object_element!(
-                                string_element!("type") => Element::StringElement(format!("{}:{}", function.namespace(), function.name())),
-                                Element::StringElement(function.argument_key().to_owned()) => *receiver
-                            ));
-                        }
-                    }
-                } else if args.len() == 1 {
-                    for function in &self.two_args_functions {
-                        if function.allow_method_syntax() && function.name() == name {
-                            return ProcessResult::from_element(object_element!(
-                                string_element!("type") => Element::StringElement(format!("{}:{}", function.namespace(), function.name())),
-                                Element::StringElement(function.argument1_key().to_owned()) => *receiver,
-                                Element::StringElement(function.argument2_key().to_owned()) => args.swap_remove(0)
-                            ));
-                        }
-                    }
-                } else if args.len() == 2 {
-                    for function in &self.custom_three_args_functions {
-                        if function.allow_method_syntax() && function.name() == name {
-                            return function.function()(*receiver, args.swap_remove(0), args.swap_remove(0));
-                        }
+                for function in &self.custom_binary_operators {
+                    if function.name() == operator.text() {
+                        return function.function()(*left, *right);
                     }
                 }
 
-                ProcessResult::from_element(Element::FunctionCallElement { receiver: Some(receiver), name, arguments: Some(args) })
-            },
-            Element::FunctionCallElement { receiver: None, name, arguments: Some(mut args) } => {
-                if args.len() == 0 {
-                    for function in &self.no_arg_functions {
-                        if function.name() == name {
-                            return ProcessResult::from_element(object_element!(
-                                string_element!("type") => Element::StringElement(format!("{}:{}", function.namespace(), function.name()))
-                            ));
-                        }
-                    }
-                } else if args.len() == 1 {
-                    for function in &self.one_arg_functions {
-                        if function.name() == name {
-                            return ProcessResult::from_element(object_element!(
-                                string_element!("type") => Element::StringElement(format!("{}:{}", function.namespace(), function.name())),
-                                Element::StringElement(function.argument_key().to_owned()) => args.swap_remove(0)
-                            ));
-                        }
-                    }
-                } else if args.len() == 2 {
-                    for function in &self.two_args_functions {
-                        if function.name() == name {
-                            return ProcessResult::from_element(object_element!(
-                                string_element!("type") => Element::StringElement(format!("{}:{}", function.namespace(), function.name())),
-                                Element::StringElement(function.argument1_key().to_owned()) => args.swap_remove(0),
-                                Element::StringElement(function.argument2_key().to_owned()) => args.swap_remove(0)
-                            ));
-                        }
-                    }
-                } else if args.len() == 3 {
-                    for function in &self.custom_three_args_functions {
-                        if function.name() == name {
-                            return function.function()(args.swap_remove(0), args.swap_remove(0), args.swap_remove(0));
-                        }
+                ProcessResult::from_element(Element::BinaryElement { left, operator, right, span })
+            }
+            Element::UnaryElement { operator, operand, span } => {
+                for function in &self.custom_unary_operators {
+                    if function.name() == operator.text() {
+                        return function.function()(*operand);
                     }
                 }
 
-                ProcessResult::from_element(Element::FunctionCallElement { receiver: None, name, arguments: Some(args) })
+                ProcessResult::from_element(Element::UnaryElement { operator, operand, span })
             }
+            Element::ConditionalElement { condition, when_true, when_false, span } => {
+                match condition::range_choice_arguments(*condition, *when_true, *when_false) {
+                    Ok(arguments) => self.pre_process(Element::FunctionCallElement {
+                        receiver: None,
+                        name: String::from("range_choice"),
+                        arguments: Some(arguments),
+                        span,
+                    }),
+                    Err(error) => {
+                        let mut result = ProcessResult::empty();
+                        result.with_error(error);
+                        result
+                    },
+                }
+            },
+            Element::SplineElement { coordinate, points, .. } => {
+                match spline::lower_spline(*coordinate, points) {
+                    Ok(spline) => ProcessResult::from_element(object_element!(
+                        string_element!("type") => string_element!("minecraft:spline"),
+                        string_element!("spline") => spline
+                    )),
+                    Err(error) => {
+                        let mut result = ProcessResult::empty();
+                        result.with_error(error);
+                        result
+                    },
+                }
+            },
+            Element::FunctionCallElement { receiver, name, arguments: Some(mut args), span } => {
+                let method = receiver.is_some();
+
+                if let Some(receiver) = receiver { // The receiver is the first argument
+                    args.insert(0, *receiver);
+                }
+
+                let functions: Vec<&Function> = self.functions.iter()
+                    .filter(|function| function.name() == name && (!method || function.allow_method_syntax()))
+                    .collect();
+
+                if functions.is_empty() {
+                    let receiver = if method { Some(Box::new(args.remove(0))) } else { None };
+                    return ProcessResult::from_element(Element::FunctionCallElement { receiver, name, arguments: Some(args), span });
+                }
+
+                match ElementProcessor::bind_to_any(&functions, args, method, span) {
+                    Ok((function, arguments)) => self.call_function(function, arguments),
+                    Err(error) => {
+                        let mut result = ProcessResult::empty();
+                        result.with_error(error);
+                        result
+                    },
+                }
+            },
             element => ProcessResult::from_element(element),
         }
     }
 
-    fn apply_processors<'b>(element: Element, processors: &Vec<Box<dyn Fn(Element) -> ProcessResult + 'b>>) -> ProcessResult {
+    // Returns the first of the functions that accepts the arguments, with the bound arguments,
+    // or the error of the first function if none of them accept the arguments
+    fn bind_to_any<'b>(functions: &[&'b Function], arguments: Vec<Element>, method: bool, span: Span) -> Result<(&'b Function, Vec<Element>), ProcessError> {
+        let mut first_error = None;
+        let mut arguments = Some(arguments);
+
+        for (i, function) in functions.iter().enumerate() {
+            // Only clone the arguments if there is another function to try afterwards
+            let arguments = if i == functions.len() - 1 { arguments.take() } else { arguments.clone() }
+                .expect("arguments are only taken for the last function");
+
+            match function.bind_arguments(arguments, method, span) {
+                Ok(bound) => return Ok((function, bound)),
+                Err(error) => { first_error.get_or_insert(error); },
+            }
+        }
+
+        Err(first_error.expect("at least one function"))
+    }
+
+    fn call_function(&self, function: &Function, arguments: Vec<Element>) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+
+        for (parameter, argument) in function.parameters().iter().zip(&arguments) {
+            if !parameter.argument_type().accepts(argument) {
+                result.with_error(ProcessError::WrongArgumentType {
+                    function: function.name().to_owned(),
+                    parameter: parameter.key().to_owned(),
+                    expected: parameter.argument_type(),
+                    got: Box::new(argument.clone()),
+                });
+            }
+        }
+
+        if !result.errors().is_empty() {
+            return result;
+        }
+
+        if let Some(custom) = function.custom() {
+            let mut custom_result = custom(arguments);
+            result.append_warnings_and_errors(&mut custom_result);
+            return result.with_element(custom_result.take_element());
+        }
+
+        let mut fields = vec![(string_element!("type"), string_element!(function.type_name()))];
+
+        for (parameter, argument) in function.parameters().iter().zip(arguments) {
+            fields.push((string_element!(parameter.key().to_owned()), argument));
+        }
+
+        result.with_element(Some(Element::ObjectElement(fields, Span::none())))
+    }
+
+    // Number arguments are accepted as operators and function calls before processing, so the
+    // ones that were not folded into a number are found in the density function object. Custom
+    // functions are checked as well if they create an object of their own type.
+    fn check_numbers(&self, element: Element) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+
+        let function = match element.field("type") {
+            Some(Element::StringElement(type_name, _)) => self.functions.iter().find(|function| function.type_name() == *type_name
+                && function.parameters().iter().all(|parameter| element.field(parameter.key()).is_some())),
+            _ => None,
+        };
+
+        if let Some(function) = function {
+            for parameter in function.parameters() {
+                let argument = element.field(parameter.key()).expect("object has a field for every parameter");
+
+                if matches!(parameter.argument_type(), ArgumentType::Number | ArgumentType::Int)
+                    && !parameter.argument_type().accepts_processed(argument) {
+                    result.with_error(ProcessError::WrongArgumentType {
+                        function: function.name().to_owned(),
+                        parameter: parameter.key().to_owned(),
+                        expected: parameter.argument_type(),
+                        got: Box::new(argument.clone()),
+                    });
+                }
+            }
+        }
+
+        result.with_element(Some(element))
+    }
+
+    fn apply_processors<'b>(element: Element, processors: &Vec<Box<dyn Fn(Element) -> ProcessResult + Send + Sync + 'b>>) -> ProcessResult {
         let mut result = ProcessResult::from_element(element);
 
         for processor in processors {
             result = result.flat_map(processor);
 
-            if let None = result.element() {
+            if result.element().is_none() {
                 return result;
             }
         }
@@ -0,0 +1,218 @@
+use std::fmt::{Display, Formatter};
+
+use crate::element::Element;
+use crate::parser::Span;
+use crate::processor::{ArgumentType, ElementProcessor, Function, Parameter};
+
+// Adds functions listed in a registry file to a processor. A registry file is a JSON object like this:
+// { "functions": [ { "name": "abs", "namespace": "minecraft", "arguments": [ "argument" ], "allow_method_syntax": true } ] }
+// "namespace" defaults to "minecraft". Instead of "arguments", "arity" can be used to get the default argument keys
+// ("argument" for one argument, "argument1", "argument2", ... for more arguments).
+// An argument can also be an object like { "key": "min", "type": "number", "default": 0.0 }, where "type" is one of
+// "density_function" (the default), "number", "int", "string", "noise_id" or "any".
+// Functions without required arguments can set "allow_as_symbol" to be usable without parentheses.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistryError {
+    NotAnObject(String, Element),
+    MissingField(String, &'static str, Span),
+    InvalidField {
+        function: String,
+        field: &'static str,
+        expected: &'static str,
+        got: Box<Element>,
+    },
+    ArityMismatch {
+        function: String,
+        arity: i32,
+        arguments: usize,
+        span: Span,
+    },
+    InvalidDefault {
+        function: String,
+        argument: String,
+        expected: ArgumentType,
+        got: Box<Element>,
+    },
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::NotAnObject(what, element) => write!(f, "Expected {} to be an object, got: {:?}", what, element),
+            RegistryError::MissingField(function, field, _) => write!(f, "Function '{}' is missing the field \"{}\"", function, field),
+            RegistryError::InvalidField { function, field, expected, got } =>
+                write!(f, "Field \"{}\" of function '{}' must be {}, got: {:?}", field, function, expected, got),
+            RegistryError::ArityMismatch { function, arity, arguments, .. } =>
+                write!(f, "Function '{}' has arity {}, but {} argument keys", function, arity, arguments),
+            RegistryError::InvalidDefault { function, argument, expected, got } =>
+                write!(f, "Default of argument '{}' of function '{}' must be {}, got: {:?}", argument, function, expected, got),
+        }
+    }
+}
+
+impl RegistryError {
+    pub fn span(&self) -> Option<Span> {
+        let span = match self {
+            RegistryError::NotAnObject(_, element) => element.span(),
+            RegistryError::InvalidField { got, .. } | RegistryError::InvalidDefault { got, .. } => got.span(),
+            RegistryError::MissingField(_, _, span) | RegistryError::ArityMismatch { span, .. } => *span,
+        };
+
+        Some(span).filter(|span| !span.is_none())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            RegistryError::NotAnObject(..) => "E0301",
+            RegistryError::MissingField(..) => "E0302",
+            RegistryError::InvalidField { .. } => "E0303",
+            RegistryError::ArityMismatch { .. } => "E0304",
+            RegistryError::InvalidDefault { .. } => "E0305",
+        }
+    }
+}
+
+pub fn load_registry(processor: &mut ElementProcessor<'_>, registry: &Element) -> Result<(), Vec<RegistryError>> {
+    if !matches!(registry, Element::ObjectElement(..)) {
+        return Err(vec![RegistryError::NotAnObject(String::from("the registry"), registry.clone())]);
+    }
+
+    let functions = match registry.field("functions") {
+        Some(Element::ArrayElement(functions, _)) => functions,
+        Some(other) => return Err(vec![RegistryError::InvalidField {
+            function: String::from("<registry>"), field: "functions", expected: "an array", got: Box::new(other.clone()),
+        }]),
+        None => return Ok(()),
+    };
+
+    let mut errors = Vec::new();
+
+    for (i, function) in functions.iter().enumerate() {
+        if let Err(error) = load_function(processor, function, i) {
+            errors.push(error);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn load_function(processor: &mut ElementProcessor<'_>, function: &Element, index: usize) -> Result<(), RegistryError> {
+    if !matches!(function, Element::ObjectElement(..)) {
+        return Err(RegistryError::NotAnObject(format!("function #{}", index), function.clone()));
+    }
+
+    let name = match function.field("name") {
+        Some(Element::StringElement(name, _)) => name.clone(),
+        Some(other) => return Err(RegistryError::InvalidField {
+            function: format!("#{}", index), field: "name", expected: "a string", got: Box::new(other.clone()),
+        }),
+        None => return Err(RegistryError::MissingField(format!("#{}", index), "name", function.span())),
+    };
+
+    let namespace = string_field(function, &name, "namespace")?.unwrap_or_else(|| String::from("minecraft"));
+    let allow_method_syntax = bool_field(function, &name, "allow_method_syntax")?;
+    let allow_as_symbol = bool_field(function, &name, "allow_as_symbol")?;
+
+    let arity = match function.field("arity") {
+        Some(Element::IntElement(arity, _)) if *arity >= 0 => Some(*arity),
+        Some(other) => return Err(RegistryError::InvalidField {
+            function: name, field: "arity", expected: "a non-negative integer", got: Box::new(other.clone()),
+        }),
+        None => None,
+    };
+
+    let parameters = match function.field("arguments") {
+        Some(Element::ArrayElement(arguments, _)) => {
+            let mut parameters = Vec::new();
+
+            for argument in arguments {
+                parameters.push(load_parameter(argument, &name)?);
+            }
+
+            if let Some(arity) = arity {
+                if arity as usize != parameters.len() {
+                    return Err(RegistryError::ArityMismatch { function: name, arity, arguments: parameters.len(), span: function.span() });
+                }
+            }
+
+            parameters
+        },
+        Some(other) => return Err(RegistryError::InvalidField {
+            function: name, field: "arguments", expected: "an array", got: Box::new(other.clone()),
+        }),
+        None => match arity.unwrap_or(0) {
+            0 => Vec::new(),
+            1 => vec![Parameter::density_function("argument")],
+            arity => (1..=arity).map(|i| Parameter::new_with_config(format!("argument{}", i), ArgumentType::DensityFunction, None)).collect(),
+        },
+    };
+
+    // Same defaults as the built-in functions: only functions with one argument can be used as methods
+    let allow_method_syntax = allow_method_syntax.unwrap_or(parameters.len() == 1);
+
+    processor.add_function(Function::new_with_config(name, namespace, parameters, allow_method_syntax, allow_as_symbol.unwrap_or(false)));
+    Ok(())
+}
+
+fn load_parameter(argument: &Element, name: &str) -> Result<Parameter, RegistryError> {
+    match argument {
+        Element::StringElement(key, _) => Ok(Parameter::new_with_config(key.clone(), ArgumentType::DensityFunction, None)),
+        Element::ObjectElement(..) => {
+            let key = string_field(argument, name, "key")?
+                .ok_or_else(|| RegistryError::MissingField(name.to_owned(), "key", argument.span()))?;
+
+            let argument_type = match string_field(argument, name, "type")?.as_deref() {
+                None | Some("density_function") => ArgumentType::DensityFunction,
+                Some("number") => ArgumentType::Number,
+                Some("int") => ArgumentType::Int,
+                Some("string") => ArgumentType::String,
+                Some("noise_id") => ArgumentType::NoiseId,
+                Some("any") => ArgumentType::Any,
+                Some(other) => return Err(RegistryError::InvalidField {
+                    function: name.to_owned(), field: "type",
+                    expected: "one of \"density_function\", \"number\", \"int\", \"string\", \"noise_id\" or \"any\"",
+                    got: Box::new(Element::StringElement(other.to_owned(), argument.field("type").map_or(Span::none(), Element::span))),
+                }),
+            };
+
+            // A default of the wrong type would fail at every call that leaves the argument out
+            let default = argument.field("default");
+
+            if let Some(default) = default.filter(|default| !argument_type.accepts(default)) {
+                return Err(RegistryError::InvalidDefault {
+                    function: name.to_owned(), argument: key, expected: argument_type, got: Box::new(default.clone()),
+                });
+            }
+
+            Ok(Parameter::new_with_config(key, argument_type, default.cloned()))
+        },
+        other => Err(RegistryError::InvalidField {
+            function: name.to_owned(), field: "arguments", expected: "an array of strings or objects", got: Box::new(other.clone()),
+        }),
+    }
+}
+
+fn string_field(function: &Element, name: &str, field: &'static str) -> Result<Option<String>, RegistryError> {
+    match function.field(field) {
+        Some(Element::StringElement(value, _)) => Ok(Some(value.clone())),
+        Some(other) => Err(RegistryError::InvalidField {
+            function: name.to_owned(), field, expected: "a string", got: Box::new(other.clone()),
+        }),
+        None => Ok(None),
+    }
+}
+
+fn bool_field(function: &Element, name: &str, field: &'static str) -> Result<Option<bool>, RegistryError> {
+    match function.field(field) {
+        Some(Element::BooleanElement(value, _)) => Ok(Some(*value)),
+        Some(other) => Err(RegistryError::InvalidField {
+            function: name.to_owned(), field, expected: "true or false", got: Box::new(other.clone()),
+        }),
+        None => Ok(None),
+    }
+}
@@ -1,6 +1,8 @@
 use std::fmt::{Display, Formatter};
 
 use crate::element::Element;
+use crate::parser::Span;
+use crate::processor::ArgumentType;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum ProcessWarning {
@@ -15,17 +17,196 @@ impl Display for ProcessWarning {
     }
 }
 
+impl ProcessWarning {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ProcessWarning::LerpDuplicatedCode(element) => Some(element.span()).filter(|span| !span.is_none()),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProcessWarning::LerpDuplicatedCode(_) => "W0001",
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ProcessError {
-    UnknownOperator(String),
-    UnknownFunction(String)
+    UnknownOperator(String, Span),
+    UnknownFunction(String, Span),
+    NonConstantDivisor(Element),
+    DivisionByZero(Span),
+    DuplicateDefinition(String, Span),
+    DuplicateParameter(String, String, Span),
+    WrongArgumentCount {
+        name: String,
+        min: usize,
+        max: usize,
+        got: usize,
+        span: Span,
+    },
+    UnknownArgument {
+        function: String,
+        name: String,
+        span: Span,
+    },
+    DuplicateArgument {
+        function: String,
+        name: String,
+        span: Span,
+    },
+    MissingArgument {
+        function: String,
+        name: String,
+        span: Span,
+    },
+    PositionalAfterNamedArgument(String, Span),
+    WrongArgumentType {
+        function: String,
+        parameter: String,
+        expected: ArgumentType,
+        got: Box<Element>,
+    },
+    RecursiveFunction(Vec<String>, Span),
+    UnsupportedCondition(Element),
+    NonConstantSplineLocation(Element),
+    UnorderedSplinePoints {
+        previous: f64,
+        location: f64,
+        span: Span,
+    },
+
+    ImportNotFound {
+        path: String,
+        chain: Vec<String>,
+    },
+    ImportFailed {
+        path: String,
+        message: String,
+        span: Span,
+    },
+    ImportCycle(Vec<String>),
+    NonIntegerGradientY(Element),
+    EmptyGradient {
+        from_y: i32,
+        to_y: i32,
+        span: Span,
+    },
+    ExpectedArrow(Element),
+    UnsupportedBound(Element),
 }
 
 impl Display for ProcessError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            ProcessError::UnknownOperator(operator) => write!(f, "Unknown operator: {}", operator),
-            ProcessError::UnknownFunction(name) => write!(f, "Unknown function: {}", name),
+            ProcessError::UnknownOperator(operator, _) => write!(f, "Unknown operator: {}", operator),
+            ProcessError::UnknownFunction(name, _) => write!(f, "Unknown function: {}", name),
+            ProcessError::NonConstantDivisor(element) => write!(f, "Only division by a constant number is supported, got: {:?}", element),
+            ProcessError::DivisionByZero(_) => write!(f, "Division by zero"),
+            ProcessError::DuplicateDefinition(name, _) => write!(f, "'{}' is already defined", name),
+            ProcessError::DuplicateParameter(function, name, _) => write!(f, "Parameter '{}' of function '{}' is declared more than once", name, function),
+            ProcessError::WrongArgumentCount { name, min, max, got, .. } => if min == max {
+                write!(f, "Function '{}' takes {} argument(s), got {}", name, min, got)
+            } else {
+                write!(f, "Function '{}' takes {} to {} arguments, got {}", name, min, max, got)
+            },
+            ProcessError::UnknownArgument { function, name, .. } => write!(f, "Function '{}' has no argument '{}'", function, name),
+            ProcessError::DuplicateArgument { function, name, .. } => write!(f, "Argument '{}' of function '{}' is given more than once", name, function),
+            ProcessError::MissingArgument { function, name, .. } => write!(f, "Missing argument '{}' of function '{}'", name, function),
+            ProcessError::PositionalAfterNamedArgument(function, _) =>
+                write!(f, "Positional arguments of function '{}' must come before named arguments", function),
+            ProcessError::WrongArgumentType { function, parameter, expected, got } =>
+                write!(f, "Argument '{}' of function '{}' must be {}, got: {:?}", parameter, function, expected, got),
+            ProcessError::RecursiveFunction(chain, _) => write!(f, "Recursive function call: {}", chain.join(" -> ")),
+            ProcessError::UnsupportedCondition(condition) =>
+                write!(f, "Conditions must have the form `min <= input < max`, `min <= input` or `input < max` with constant bounds, got: {:?}", condition),
+            ProcessError::NonConstantSplineLocation(location) => write!(f, "Spline locations must be constant numbers, got: {:?}", location),
+            ProcessError::UnorderedSplinePoints { previous, location, .. } =>
+                write!(f, "Spline points must be ordered by location, but {} comes after {}", location, previous),
+            ProcessError::ImportNotFound { path, chain } =>
+                write!(f, "Imported file not found: \"{}\" (imported by {})", path, chain.join(" -> ")),
+            ProcessError::ImportFailed { path, message, .. } => write!(f, "Failed to import {}: {}", path, message),
+            ProcessError::ImportCycle(chain) => write!(f, "Import cycle: {}", chain.join(" -> ")),
+            ProcessError::NonIntegerGradientY(y) => write!(f, "Y values of a gradient must be integer constants, got: {:?}", y),
+            ProcessError::EmptyGradient { from_y, to_y, .. } => write!(f, "from_y ({}) of a gradient must be below to_y ({})", from_y, to_y),
+            ProcessError::ExpectedArrow(element) => write!(f, "Expected `y -> value`, got: {:?}", element),
+            ProcessError::UnsupportedBound(condition) =>
+                write!(f, "Only `min <= input < max` is supported, as range_choice includes the minimum and excludes the maximum, got: {:?}", condition),
+        }
+    }
+}
+
+impl ProcessError {
+    // Where in the source the error was found; errors about imports have no position
+    pub fn span(&self) -> Option<Span> {
+        let span = match self {
+            ProcessError::NonConstantDivisor(element)
+            | ProcessError::UnsupportedCondition(element)
+            | ProcessError::NonConstantSplineLocation(element)
+            | ProcessError::NonIntegerGradientY(element)
+            | ProcessError::ExpectedArrow(element)
+            | ProcessError::UnsupportedBound(element) => element.span(),
+            ProcessError::WrongArgumentType { got, .. } => got.span(),
+            ProcessError::UnknownOperator(_, span)
+            | ProcessError::UnknownFunction(_, span)
+            | ProcessError::DivisionByZero(span)
+            | ProcessError::DuplicateDefinition(_, span)
+            | ProcessError::DuplicateParameter(_, _, span)
+            | ProcessError::PositionalAfterNamedArgument(_, span)
+            | ProcessError::RecursiveFunction(_, span)
+            | ProcessError::WrongArgumentCount { span, .. }
+            | ProcessError::UnknownArgument { span, .. }
+            | ProcessError::DuplicateArgument { span, .. }
+            | ProcessError::MissingArgument { span, .. }
+            | ProcessError::EmptyGradient { span, .. }
+            | ProcessError::UnorderedSplinePoints { span, .. }
+            | ProcessError::ImportFailed { span, .. } => *span,
+            ProcessError::ImportNotFound { .. } | ProcessError::ImportCycle(_) => return None,
+        };
+
+        Some(span).filter(|span| !span.is_none())
+    }
+
+    pub fn involves_error_element(&self) -> bool {
+        match self {
+            ProcessError::NonConstantDivisor(element)
+            | ProcessError::UnsupportedCondition(element)
+            | ProcessError::NonConstantSplineLocation(element)
+            | ProcessError::NonIntegerGradientY(element)
+            | ProcessError::ExpectedArrow(element)
+            | ProcessError::UnsupportedBound(element) => element.contains_error(),
+            ProcessError::WrongArgumentType { got, .. } => got.contains_error(),
+            _ => false,
+        }
+    }
+
+    // Stable codes for diagnostics, new variants get new codes
+    pub fn code(&self) -> &'static str {
+        match self {
+            ProcessError::UnknownOperator(..) => "E0101",
+            ProcessError::UnknownFunction(..) => "E0102",
+            ProcessError::NonConstantDivisor(_) => "E0103",
+            ProcessError::DivisionByZero(_) => "E0104",
+            ProcessError::DuplicateDefinition(..) => "E0105",
+            ProcessError::DuplicateParameter(..) => "E0106",
+            ProcessError::WrongArgumentCount { .. } => "E0107",
+            ProcessError::UnknownArgument { .. } => "E0108",
+            ProcessError::DuplicateArgument { .. } => "E0109",
+            ProcessError::MissingArgument { .. } => "E0110",
+            ProcessError::PositionalAfterNamedArgument(..) => "E0111",
+            ProcessError::WrongArgumentType { .. } => "E0112",
+            ProcessError::RecursiveFunction(..) => "E0113",
+            ProcessError::UnsupportedCondition(_) => "E0114",
+            ProcessError::NonConstantSplineLocation(_) => "E0115",
+            ProcessError::UnorderedSplinePoints { .. } => "E0116",
+            ProcessError::ImportNotFound { .. } => "E0120",
+            ProcessError::ImportFailed { .. } => "E0121",
+            ProcessError::ImportCycle(_) => "E0122",
+            ProcessError::NonIntegerGradientY(_) => "E0117",
+            ProcessError::EmptyGradient { .. } => "E0118",
+            ProcessError::ExpectedArrow(_) => "E0119",
+            ProcessError::UnsupportedBound(_) => "E0123",
         }
     }
 }
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use crate::element::{Element, SplinePoint};
+use crate::parser::Span;
+use crate::parser::lexer::LangToken;
+use crate::processor::{ArgumentType, Function, Parameter, ProcessError, ProcessResult};
+
+// A function defined in the language itself, which is expanded at every call
+#[derive(Clone, Debug, PartialEq)]
+pub struct MacroFunction {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub body: Element,
+}
+
+impl MacroFunction {
+    pub fn new(name: String, parameters: Vec<String>, body: Element) -> MacroFunction {
+        MacroFunction {
+            name, parameters, body
+        }
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn parameters(&self) -> &[String] { &self.parameters }
+    pub fn body(&self) -> &Element { &self.body }
+}
+
+// State of a single expansion: the arguments of the function being expanded,
+// and the chain of functions that led to it (for recursion detection)
+struct Expansion<'a> {
+    arguments: HashMap<&'a str, Element>,
+    stack: Vec<&'a str>,
+}
+
+// Definitions that names in a module can refer to
+pub struct Scope {
+    constants: HashMap<String, Element>,
+    functions: HashMap<String, MacroFunction>,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scope {
+    pub fn new() -> Scope {
+        Scope {
+            constants: HashMap::new(),
+            functions: HashMap::new(),
+        }
+    }
+
+    pub fn constant(&self, name: &str) -> Option<&Element> {
+        self.constants.get(name)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&MacroFunction> {
+        self.functions.get(name)
+    }
+
+    fn is_defined(&self, name: &str) -> bool {
+        self.constants.contains_key(name) || self.functions.contains_key(name)
+    }
+
+    pub fn define_constant(&mut self, name: String, value: Element) -> Result<(), ProcessError> {
+        if self.is_defined(&name) {
+            return Err(ProcessError::DuplicateDefinition(name, value.span()));
+        }
+
+        self.constants.insert(name, value);
+        Ok(())
+    }
+
+    pub fn define_function(&mut self, function: MacroFunction) -> Result<(), ProcessError> {
+        if self.is_defined(function.name()) {
+            return Err(ProcessError::DuplicateDefinition(function.name.clone(), function.body.span()));
+        }
+
+        for (i, parameter) in function.parameters().iter().enumerate() {
+            if function.parameters()[..i].contains(parameter) {
+                return Err(ProcessError::DuplicateParameter(function.name.clone(), parameter.clone(), function.body.span()));
+            }
+        }
+
+        self.functions.insert(function.name.clone(), function);
+        Ok(())
+    }
+
+    // Replaces every name referring to a constant by its value, and expands calls to macro functions
+    pub fn resolve(&self, element: Element) -> ProcessResult {
+        self.resolve_in(element, &Expansion { arguments: HashMap::new(), stack: Vec::new() })
+    }
+
+    // Each kind of element is resolved in a function of its own, which keeps the stack small for
+    // deeply nested elements
+    fn resolve_in<'a>(&'a self, element: Element, expansion: &Expansion<'a>) -> ProcessResult {
+        match element {
+            Element::NameElement(name, span) => match expansion.arguments.get(name.as_str()).or_else(|| self.constant(&name)) {
+                Some(value) => ProcessResult::from_element(value.clone()),
+                None => ProcessResult::from_element(Element::NameElement(name, span)),
+            },
+            Element::ArrayElement(values, span) => self.resolve_all(values, expansion)
+                .map(|values| values.with_span(span)),
+            Element::ObjectElement(fields, span) => self.resolve_object(fields, span, expansion),
+            Element::UnaryElement { operator, operand, span } => self.resolve_in(*operand, expansion)
+                .map(|operand| Element::UnaryElement { operator, operand: Box::new(operand), span }),
+            Element::BinaryElement { left, operator, right, span } => self.resolve_binary(*left, operator, *right, span, expansion),
+            Element::ConditionalElement { condition, when_true, when_false, span } =>
+                self.resolve_conditional(*condition, *when_true, *when_false, span, expansion),
+            Element::SplineElement { coordinate, points, span } => self.resolve_spline(*coordinate, points, span, expansion),
+            Element::NamedArgumentElement { name, value, span } => self.resolve_in(*value, expansion)
+                .map(|value| Element::NamedArgumentElement { name, value: Box::new(value), span }),
+            Element::FunctionCallElement { receiver, name, arguments, span } => self.resolve_call(receiver, name, arguments, span, expansion),
+            element => ProcessResult::from_element(element),
+        }
+    }
+
+    fn resolve_object<'a>(&'a self, fields: Vec<(Element, Element)>, span: Span, expansion: &Expansion<'a>) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut result_fields = Vec::new();
+
+        for (key, value) in fields {
+            // Keys are not resolved, so that `{ name: ... }` stays the same if there is a constant called `name`
+            let mut value_result = self.resolve_in(value, expansion);
+            result.append_warnings_and_errors(&mut value_result);
+
+            match value_result.take_element() {
+                Some(value) => result_fields.push((key, value)),
+                None => return result,
+            }
+        }
+
+        result.with_element(Some(Element::ObjectElement(result_fields, span)))
+    }
+
+    fn resolve_binary<'a>(&'a self, left: Element, operator: LangToken, right: Element, span: Span, expansion: &Expansion<'a>) -> ProcessResult {
+        let mut result = self.resolve_in(left, expansion);
+        let mut right_result = self.resolve_in(right, expansion);
+        result.append_warnings_and_errors(&mut right_result);
+
+        match (result.take_element(), right_result.take_element()) {
+            (Some(left), Some(right)) => result.with_element(Some(Element::BinaryElement {
+                left: Box::new(left), operator, right: Box::new(right), span,
+            })),
+            _ => result,
+        }
+    }
+
+    fn resolve_conditional<'a>(&'a self, condition: Element, when_true: Element, when_false: Element, span: Span, expansion: &Expansion<'a>) -> ProcessResult {
+        let mut result = self.resolve_all(vec![condition, when_true, when_false], expansion);
+
+        match result.take_element() {
+            Some(Element::ArrayElement(elements, _)) => {
+                let [condition, when_true, when_false]: [Element; 3] = elements.try_into().expect("three elements");
+                result.with_element(Some(Element::ConditionalElement {
+                    condition: Box::new(condition), when_true: Box::new(when_true), when_false: Box::new(when_false), span,
+                }))
+            },
+            _ => result,
+        }
+    }
+
+    fn resolve_spline<'a>(&'a self, coordinate: Element, points: Vec<SplinePoint>, span: Span, expansion: &Expansion<'a>) -> ProcessResult {
+        let mut result = self.resolve_in(coordinate, expansion);
+        let coordinate = match result.take_element() {
+            Some(coordinate) => coordinate,
+            None => return result,
+        };
+
+        let mut result_points = Vec::new();
+
+        for point in points {
+            let mut elements = vec![point.location, point.value];
+            elements.extend(point.derivative);
+
+            let mut point_result = self.resolve_all(elements, expansion);
+            result.append_warnings_and_errors(&mut point_result);
+
+            match point_result.take_element() {
+                Some(Element::ArrayElement(elements, _)) => {
+                    let mut elements = elements.into_iter();
+                    result_points.push(SplinePoint {
+                        location: elements.next().expect("location"),
+                        value: elements.next().expect("value"),
+                        derivative: elements.next(),
+                    });
+                },
+                _ => return result,
+            }
+        }
+
+        result.with_element(Some(Element::SplineElement { coordinate: Box::new(coordinate), points: result_points, span }))
+    }
+
+    fn resolve_call<'a>(&'a self, receiver: Option<Box<Element>>, name: String, arguments: Option<Vec<Element>>, span: Span,
+                        expansion: &Expansion<'a>) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+
+        let receiver = match receiver {
+            Some(receiver) => {
+                let mut receiver_result = self.resolve_in(*receiver, expansion);
+                result.append_warnings_and_errors(&mut receiver_result);
+
+                match receiver_result.take_element() {
+                    Some(receiver) => Some(receiver),
+                    None => return result,
+                }
+            },
+            None => None,
+        };
+
+        let arguments = match arguments {
+            Some(arguments) => {
+                let mut arguments_result = self.resolve_all(arguments, expansion);
+                result.append_warnings_and_errors(&mut arguments_result);
+
+                match arguments_result.take_element() {
+                    Some(Element::ArrayElement(arguments, _)) => Some(arguments),
+                    _ => return result,
+                }
+            },
+            None => None,
+        };
+
+        match (self.function(&name), arguments) {
+            (Some(function), Some(mut arguments)) => {
+                if let Some(receiver) = receiver { // Method syntax; the receiver is the first argument
+                    arguments.insert(0, receiver);
+                }
+
+                let mut expansion_result = self.expand(function, arguments, span, expansion);
+                result.append_warnings_and_errors(&mut expansion_result);
+                result.with_element(expansion_result.take_element())
+            },
+            (_, arguments) => result.with_element(Some(Element::FunctionCallElement {
+                receiver: receiver.map(Box::new), name, arguments, span,
+            })),
+        }
+    }
+
+    fn expand<'a>(&'a self, function: &'a MacroFunction, arguments: Vec<Element>, span: Span, expansion: &Expansion<'a>) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+
+        if expansion.stack.contains(&function.name()) {
+            let mut chain: Vec<String> = expansion.stack.iter()
+                .skip_while(|name| **name != function.name())
+                .map(|name| name.to_string())
+                .collect();
+            chain.push(function.name.clone());
+
+            result.with_error(ProcessError::RecursiveFunction(chain, span));
+            return result;
+        }
+
+        // Named arguments are matched the same way as for built-in functions
+        let signature = Function::new(function.name.clone(), function.parameters().iter()
+            .map(|parameter| Parameter::new_with_config(parameter.clone(), ArgumentType::Any, None))
+            .collect());
+
+        let arguments = match signature.bind_arguments(arguments, false, span) {
+            Ok(arguments) => arguments,
+            Err(error) => {
+                result.with_error(error);
+                return result;
+            },
+        };
+
+        let mut stack = expansion.stack.clone();
+        stack.push(function.name());
+
+        let inner = Expansion {
+            arguments: function.parameters().iter().map(String::as_str).zip(arguments).collect(),
+            stack,
+        };
+
+        self.resolve_in(function.body().clone(), &inner)
+    }
+
+    // Returns the resolved elements as an array element
+    fn resolve_all<'a>(&'a self, elements: Vec<Element>, expansion: &Expansion<'a>) -> ProcessResult {
+        let mut result = ProcessResult::empty();
+        let mut result_elements = Vec::new();
+
+        for element in elements {
+            let mut element_result = self.resolve_in(element, expansion);
+            result.append_warnings_and_errors(&mut element_result);
+
+            match element_result.take_element() {
+                Some(element) => result_elements.push(element),
+                None => return result,
+            }
+        }
+
+        result.with_element(Some(Element::ArrayElement(result_elements, Span::none())))
+    }
+}
@@ -0,0 +1,43 @@
+use crate::element::{Element, SplinePoint};
+use crate::parser::Span;
+use crate::processor::ProcessError;
+use crate::{object_element, string_element};
+
+// Lowers a spline literal to the `{ "coordinate", "points" }` structure of a spline,
+// including nested splines in the values. Only the outermost spline needs to be wrapped
+// in a `minecraft:spline` density function.
+pub fn lower_spline(coordinate: Element, points: Vec<SplinePoint>) -> Result<Element, ProcessError> {
+    let mut previous_location = None;
+    let mut result_points = Vec::new();
+
+    for point in points {
+        let location = match point.location.as_number() {
+            Some(location) => location,
+            None => return Err(ProcessError::NonConstantSplineLocation(point.location)),
+        };
+
+        if let Some(previous) = previous_location {
+            if location < previous {
+                return Err(ProcessError::UnorderedSplinePoints { previous, location, span: point.location.span() });
+            }
+        }
+
+        previous_location = Some(location);
+
+        let value = match point.value {
+            Element::SplineElement { coordinate, points, span } => lower_spline(*coordinate, points)?.inherit_span(span),
+            value => value,
+        };
+
+        result_points.push(object_element!(
+            string_element!("location") => point.location,
+            string_element!("value") => value,
+            string_element!("derivative") => point.derivative.unwrap_or(Element::FloatElement(0.0, Span::none()))
+        ));
+    }
+
+    Ok(object_element!(
+        string_element!("coordinate") => coordinate,
+        string_element!("points") => Element::ArrayElement(result_points, Span::none())
+    ))
+}
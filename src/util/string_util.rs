@@ -4,12 +4,12 @@ use std::num::ParseIntError;
 
 #[inline]
 pub fn is_digit(c: char) -> bool {
-    c >= '0' && c <= '9'
+    c.is_ascii_digit()
 }
 
 #[inline]
 pub fn is_alpha(c: char) -> bool {
-    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '$' || c == '_'
+    c.is_ascii_alphabetic() || c == '$' || c == '_'
 }
 
 #[inline]
@@ -17,6 +17,15 @@ pub fn is_alpha_numeric(c: char) -> bool {
     is_digit(c) || is_alpha(c)
 }
 
+// Checks for a Minecraft resource location like `minecraft:ridge` (the namespace is optional)
+pub fn is_resource_location(input: &str) -> bool {
+    let (namespace, path) = input.split_once(':').unwrap_or(("minecraft", input));
+
+    !path.is_empty()
+        && namespace.chars().all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-' | '.'))
+        && path.chars().all(|c| matches!(c, 'a'..='z' | '0'..='9' | '_' | '-' | '.' | '/'))
+}
+
 pub enum EscapeError {
     UnexpectedEof,
     UnexpectedCharacter(i32, char),
@@ -58,22 +67,17 @@ pub fn unescape_str(input: &str) -> Result<String, EscapeError> {
             'r' => output.push('\r'),
             't' => output.push('\t'),
             'u' => {
-                let mut string = String::new();
+                let mut units = vec![read_utf16_unit(&mut chars)?];
 
-                for i in 0..4 {
-                    let c = chars.next().ok_or(EscapeError::UnexpectedEof)?;
-
-                    if c.is_digit(16) {
-                        string.push(c);
-                    } else {
-                        return Err(EscapeError::UnexpectedCharacter(1 + i, c));
-                    }
+                // Characters outside the BMP are escaped as a surrogate pair
+                if (0xD800..=0xDBFF).contains(&units[0]) && chars.as_str().starts_with("\\u") {
+                    chars.nth(1);
+                    units.push(read_utf16_unit(&mut chars)?);
                 }
 
-                output.push(char::decode_utf16([u16::from_str_radix(&string, 16)
-                    .map_err(|err| EscapeError::FailedConversion(err))?]).next()
-                    .ok_or_else(|| EscapeError::UnexpectedEof)?
-                    .map_err(|err| EscapeError::InvalidCharacter(err))?);
+                output.push(char::decode_utf16(units).next()
+                    .ok_or(EscapeError::UnexpectedEof)?
+                    .map_err(EscapeError::InvalidCharacter)?);
             }
 
             _ => return Err(EscapeError::UnexpectedCharacter(0, c)), // TODO error pos
@@ -81,6 +85,23 @@ pub fn unescape_str(input: &str) -> Result<String, EscapeError> {
     }
 }
 
+// Reads the four hex digits after `\u`
+fn read_utf16_unit(chars: &mut std::str::Chars<'_>) -> Result<u16, EscapeError> {
+    let mut string = String::new();
+
+    for i in 0..4 {
+        let c = chars.next().ok_or(EscapeError::UnexpectedEof)?;
+
+        if c.is_ascii_hexdigit() {
+            string.push(c);
+        } else {
+            return Err(EscapeError::UnexpectedCharacter(1 + i, c));
+        }
+    }
+
+    u16::from_str_radix(&string, 16).map_err(EscapeError::FailedConversion)
+}
+
 pub fn escape_character(c: char) -> String {
     match c {
         '"' => "\\\"".to_string(),
@@ -92,11 +113,15 @@ pub fn escape_character(c: char) -> String {
         '\t' => "\\t".to_string(),
         '\x20'..='\x7e' => c.to_string(),
         _ => {
-            let string = format!("{:x}", c as u16);
-            let mut result = String::with_capacity(6);
-            result.push_str("\\u");
-            result.push_str(&"0".repeat(cmp::max(0, 4 - (string.len() as i32)) as usize));
-            result.push_str(&string);
+            // Characters outside the BMP are written as a surrogate pair
+            let mut result = String::with_capacity(12);
+
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                let string = format!("{:x}", unit);
+                result.push_str("\\u");
+                result.push_str(&"0".repeat(cmp::max(0, 4 - (string.len() as i32)) as usize));
+                result.push_str(&string);
+            }
 
             result
         }
@@ -112,3 +137,34 @@ pub fn escape_str(input: &str) -> String {
 
     output
 }
+
+// Number of single character insertions, deletions and substitutions to turn `a` into `b`
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + if a_char == *b_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+// The candidate most similar to `name`, if it is close enough to be a typo
+pub fn closest_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = cmp::max(1, name.chars().count() / 3);
+
+    candidates.into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::io;
+
+// Files that were added, changed or removed since the last poll, as canonical paths
+#[derive(Debug, Default)]
+pub struct Changes {
+    pub added: Vec<PathBuf>,
+    pub changed: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+// Finds changes in directories by polling, comparing the modification times and sizes of
+// their files with the previous poll
+pub struct FileWatcher {
+    directories: Vec<PathBuf>,
+    files: HashMap<PathBuf, (SystemTime, u64)>,
+}
+
+impl FileWatcher {
+    pub fn new(directories: Vec<PathBuf>) -> FileWatcher {
+        let mut watcher = FileWatcher { directories, files: HashMap::new() };
+        watcher.files = watcher.scan();
+        watcher
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    // Files that can't be read right now (for example while they are being saved) are skipped
+    // and picked up by a later poll
+    fn scan(&self) -> HashMap<PathBuf, (SystemTime, u64)> {
+        let mut files = HashMap::new();
+
+        for directory in &self.directories {
+            for file in io::list_files(directory).unwrap_or_default() {
+                let path = directory.join(file);
+                let state = path.canonicalize().and_then(|path| Ok((path.metadata()?, path)));
+
+                if let Ok((metadata, path)) = state {
+                    files.insert(path, (metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH), metadata.len()));
+                }
+            }
+        }
+
+        files
+    }
+
+    pub fn poll(&mut self) -> Changes {
+        let files = self.scan();
+        let mut changes = Changes::default();
+
+        for (path, state) in &files {
+            match self.files.get(path) {
+                None => changes.added.push(path.clone()),
+                Some(previous) if previous != state => changes.changed.push(path.clone()),
+                _ => {},
+            }
+        }
+
+        changes.removed = self.files.keys().filter(|path| !files.contains_key(*path)).cloned().collect();
+        self.files = files;
+
+        changes.added.sort();
+        changes.changed.sort();
+        changes.removed.sort();
+        changes
+    }
+}
+
+// The files each input file was compiled from (itself and its imports), so that the files that
+// depend on a changed file can be rebuilt
+#[derive(Debug, Default)]
+pub struct Dependencies {
+    files: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl Dependencies {
+    pub fn new() -> Dependencies {
+        Dependencies { files: HashMap::new() }
+    }
+
+    pub fn set(&mut self, file: PathBuf, dependencies: Vec<PathBuf>) {
+        self.files.insert(file, dependencies);
+    }
+
+    pub fn remove(&mut self, file: &Path) {
+        self.files.remove(file);
+    }
+
+    // Input files that were compiled from any of the given files, directly or through imports
+    pub fn dependents(&self, changed: &[PathBuf]) -> Vec<PathBuf> {
+        let mut dependents: Vec<PathBuf> = self.files.iter()
+            .filter(|(_, dependencies)| dependencies.iter().any(|dependency| changed.contains(dependency)))
+            .map(|(file, _)| file.clone())
+            .collect();
+
+        dependents.sort();
+        dependents
+    }
+}
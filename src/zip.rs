@@ -0,0 +1,175 @@
+use std::fmt::{Display, Formatter};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+const VERSION: u16 = 10; // 1.0, stored entries only
+const UTF8_NAMES: u16 = 1 << 11;
+
+// All entries get the same time (1980-01-01 00:00, the earliest DOS date), so that compiling
+// the same files gives the same zip
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+#[derive(Debug)]
+pub enum Error {
+    TooManyEntries,
+    TooLarge(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::TooManyEntries => write!(f, "Too many files for a zip file (at most {})", u16::MAX),
+            Error::TooLarge(name) => write!(f, "File is too large for a zip file: {}", name),
+        }
+    }
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+// The CRC-32 checksum used by zip files
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| CRC_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+// Writes a zip file in memory, without compression. Entry names use `/` as separator.
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    pub fn new() -> ZipWriter {
+        ZipWriter { data: Vec::new(), entries: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.name == name)
+    }
+
+    pub fn add(&mut self, name: &str, contents: &[u8]) -> Result<(), Error> {
+        if self.entries.len() >= u16::MAX as usize {
+            return Err(Error::TooManyEntries);
+        }
+
+        let too_large = || Error::TooLarge(name.to_owned());
+        let size = u32::try_from(contents.len()).map_err(|_| too_large())?;
+        let offset = u32::try_from(self.data.len()).map_err(|_| too_large())?;
+        let name_length = u16::try_from(name.len()).map_err(|_| too_large())?;
+        let crc = crc32(contents);
+
+        // The local header is 30 bytes
+        if u32::try_from(self.data.len() + 30 + name.len() + contents.len()).is_err() {
+            return Err(too_large());
+        }
+
+        self.write_u32(LOCAL_HEADER_SIGNATURE);
+        self.write_u16(VERSION);
+        self.write_u16(UTF8_NAMES);
+        self.write_u16(0); // Stored
+        self.write_u16(DOS_TIME);
+        self.write_u16(DOS_DATE);
+        self.write_u32(crc);
+        self.write_u32(size); // Compressed size
+        self.write_u32(size);
+        self.write_u16(name_length);
+        self.write_u16(0); // Extra field length
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(contents);
+
+        self.entries.push(Entry { name: name.to_owned(), crc, size, offset });
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<Vec<u8>, Error> {
+        let central_directory_offset = self.data.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+
+        for entry in &entries {
+            self.write_u32(CENTRAL_HEADER_SIGNATURE);
+            self.write_u16(VERSION); // Version made by
+            self.write_u16(VERSION);
+            self.write_u16(UTF8_NAMES);
+            self.write_u16(0); // Stored
+            self.write_u16(DOS_TIME);
+            self.write_u16(DOS_DATE);
+            self.write_u32(entry.crc);
+            self.write_u32(entry.size);
+            self.write_u32(entry.size);
+            self.write_u16(entry.name.len() as u16);
+            self.write_u16(0); // Extra field length
+            self.write_u16(0); // Comment length
+            self.write_u16(0); // Disk number
+            self.write_u16(0); // Internal attributes
+            self.write_u32(0); // External attributes
+            self.write_u32(entry.offset);
+            self.data.extend_from_slice(entry.name.as_bytes());
+        }
+
+        let central_directory_size = u32::try_from(self.data.len() - central_directory_offset as usize)
+            .map_err(|_| Error::TooLarge(String::from("central directory")))?;
+        let entries = entries.len() as u16;
+
+        self.write_u32(END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        self.write_u16(0); // Disk number
+        self.write_u16(0); // Disk with the central directory
+        self.write_u16(entries);
+        self.write_u16(entries);
+        self.write_u32(central_directory_size);
+        self.write_u32(central_directory_offset);
+        self.write_u16(0); // Comment length
+
+        Ok(self.data)
+    }
+
+    fn write_u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+impl Default for ZipWriter {
+    fn default() -> Self {
+        ZipWriter::new()
+    }
+}
@@ -0,0 +1,66 @@
+// Errors that are found when compiling a file
+use std::path::Path;
+
+use worldgen_lang::{builtins, format};
+use worldgen_lang::compile::{compile_file, Compilation};
+use worldgen_lang::import::Importer;
+use worldgen_lang::io::FileResult;
+
+fn compile(source: &str) -> Compilation {
+    let importer = Importer::new(std::env::temp_dir());
+    let processor = builtins::create_processor();
+
+    compile_file(Path::new("test.json"), String::from(source), &importer, &processor, format::Options::default())
+}
+
+fn errors(source: &str) -> Vec<String> {
+    let compilation = compile(source);
+    assert!(matches!(compilation.result, FileResult::Failed), "{} compiled", source);
+
+    compilation.diagnostics.iter().map(|diagnostic| format!("{}: {}", diagnostic.code(), diagnostic.message())).collect()
+}
+
+#[test]
+fn test_density_function_as_number_argument() {
+    assert_eq!(errors(r#"clamp("minecraft:y", abs("minecraft:x"), 1)"#), vec![
+        r#"E0112: Argument 'min' of function 'clamp' must be a number, got: { "type": "minecraft:abs", "argument": "minecraft:x" }"#,
+    ]);
+
+    // Operators and function calls that are folded into a number are fine
+    let compilation = compile(r#"clamp("minecraft:y", -abs(2), 1 + 1)"#);
+    assert!(matches!(compilation.result, FileResult::Output(_)), "{:?}", compilation.diagnostics.iter().map(|diagnostic| diagnostic.message()).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_gradient_values_are_numbers() {
+    assert_eq!(errors(r#"y_gradient(-64 -> "high", 320 -> -1)"#), vec![
+        r#"E0112: Argument 'from_value' of function 'y_gradient' must be a number, got: "high""#,
+    ]);
+    assert_eq!(errors(r#"y_clamped_gradient(-64, 320, abs("minecraft:x"), 1)"#).len(), 1);
+    assert!(matches!(compile("y_gradient(-64 -> 1.5 * 2, 320 -> -1)").result, FileResult::Output(_)));
+}
+
+#[test]
+fn test_condition_bounds() {
+    for source in [r#"if "minecraft:y" >= 0 then 1 else 2"#, r#"if 0 <= "minecraft:y" then 1 else 2"#, r#"if "minecraft:y" < 64 then 1 else 2"#,
+                   r#"if 64 > "minecraft:y" then 1 else 2"#, r#"if 0 <= "minecraft:y" < 64 then 1 else 2"#] {
+        assert!(matches!(compile(source).result, FileResult::Output(_)), "{} failed", source);
+    }
+
+    for source in [r#"if "minecraft:y" > 0 then 1 else 2"#, r#"if 0 < "minecraft:y" then 1 else 2"#, r#"if "minecraft:y" <= 64 then 1 else 2"#,
+                   r#"if 0 < "minecraft:y" <= 64 then 1 else 2"#] {
+        let errors = errors(source);
+        assert_eq!(errors.len(), 1, "{}", source);
+        assert!(errors[0].starts_with("E0123: Only `min <= input < max` is supported"), "{}", errors[0]);
+    }
+}
+
+#[test]
+fn test_all_unresolved_names() {
+    let compilation = compile(r#"{ "a": cach_2d(1), "b": interpolatd(2) }"#);
+    assert!(matches!(compilation.result, FileResult::Failed));
+
+    let messages: Vec<_> = compilation.diagnostics.iter().map(|diagnostic| diagnostic.message()).collect();
+    assert_eq!(messages.len(), 2, "{:?}", messages);
+    assert!(messages[0].contains("cach_2d") && messages[1].contains("interpolatd"), "{:?}", messages);
+}
@@ -0,0 +1,95 @@
+// Decompiling density function JSON into source files, which compile back to the same JSON
+use std::path::Path;
+
+use worldgen_lang::{builtins, decompile, format, io};
+use worldgen_lang::compile::compile_file;
+use worldgen_lang::diff::diff;
+use worldgen_lang::element::Element;
+use worldgen_lang::import::Importer;
+use worldgen_lang::io::FileResult;
+use worldgen_lang::parser::LangParser;
+use worldgen_lang::parser::lexer::LangLexer;
+
+fn parse_json(json: &str) -> Element {
+    LangParser::new(LangLexer::new(json)).parse_full().unwrap_or_else(|err| panic!("Failed to parse {}: {}", json, err))
+}
+
+fn decompile_source(json: &str) -> String {
+    let element = decompile::decompile(&builtins::create_processor(), parse_json(json));
+    format::source::format_source(&element).unwrap_or_else(|err| panic!("Failed to format {}: {}", json, err))
+}
+
+// Source files end with a line break
+fn decompile_expression(json: &str) -> String {
+    decompile_source(json).trim_end().to_owned()
+}
+
+fn compile(source: &str) -> Element {
+    let importer = Importer::new(std::env::temp_dir());
+    let compilation = compile_file(Path::new("test.json"), String::from(source), &importer, &builtins::create_processor(), format::Options::default());
+
+    match compilation.result {
+        FileResult::Output(json) => parse_json(&json),
+        _ => panic!("Failed to compile:\n{}\n{:?}", source, compilation.diagnostics.iter().map(|diagnostic| diagnostic.message()).collect::<Vec<_>>()),
+    }
+}
+
+#[test]
+fn test_vanilla_round_trip() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("test/vanilla/output");
+    let files = io::list_files(&directory).unwrap_or_else(|err| panic!("Could not list vanilla files: {}", err));
+    assert!(!files.is_empty());
+
+    for file in files {
+        let json = std::fs::read_to_string(directory.join(&file)).expect("read vanilla file");
+        let source = decompile_source(&json);
+        let differences = diff(&parse_json(&json), &compile(&source), 0.0);
+
+        assert!(differences.is_empty(), "{} changed after decompiling:\n{}\n\n{}", file.display(),
+            differences.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n"), source);
+    }
+}
+
+#[test]
+fn test_method_syntax() {
+    assert_eq!(decompile_expression(r#"{ "type": "minecraft:flat_cache", "argument": { "type": "minecraft:abs", "argument": "minecraft:y" } }"#),
+        r#""minecraft:y".abs().flat_cache()"#);
+}
+
+#[test]
+fn test_trailing_defaults_are_left_out() {
+    assert_eq!(decompile_expression(r#"{ "type": "minecraft:noise", "noise": "minecraft:ridge", "xz_scale": 1, "y_scale": 1.0 }"#),
+        r#"noise("minecraft:ridge")"#);
+    assert_eq!(decompile_expression(r#"{ "type": "minecraft:noise", "noise": "minecraft:ridge", "xz_scale": 0.25, "y_scale": 1.0 }"#),
+        r#"noise("minecraft:ridge", 0.25)"#);
+}
+
+#[test]
+fn test_subtraction() {
+    let json = r#"{ "type": "minecraft:add", "argument1": "minecraft:y", "argument2": { "type": "minecraft:mul", "argument1": "minecraft:x", "argument2": -1.0 } }"#;
+
+    assert_eq!(decompile_expression(json), r#""minecraft:y" - "minecraft:x""#);
+    assert!(diff(&parse_json(json), &compile(&decompile_source(json)), 0.0).is_empty());
+}
+
+#[test]
+fn test_range_choice_with_open_bounds() {
+    let range_choice = |min: &str, max: &str| decompile_expression(&format!(r#"{{ "type": "minecraft:range_choice", "input": "minecraft:y",
+        "min_inclusive": {}, "max_exclusive": {}, "when_in_range": 1, "when_out_of_range": 0 }}"#, min, max));
+
+    assert_eq!(range_choice("-1000000.0", "64"), r#"if "minecraft:y" < 64 then 1 else 0"#);
+    assert_eq!(range_choice("0", "1000000"), r#"if 0 <= "minecraft:y" then 1 else 0"#);
+    assert_eq!(range_choice("0", "64"), r#"if 0 <= "minecraft:y" < 64 then 1 else 0"#);
+}
+
+#[test]
+fn test_lerp_in_vanilla_operand_order() {
+    let cached_alpha = r#"{ "type": "minecraft:cache_once", "argument": { "type": "minecraft:blend_alpha" } }"#;
+    let json = format!(r#"{{ "type": "minecraft:add",
+        "argument1": {{ "type": "minecraft:mul", "argument1": {{ "type": "minecraft:blend_offset" }},
+            "argument2": {{ "type": "minecraft:add", "argument1": 1.0, "argument2": {{ "type": "minecraft:mul", "argument1": -1.0, "argument2": {0} }} }} }},
+        "argument2": {{ "type": "minecraft:mul", "argument1": 30.5, "argument2": {0} }} }}"#, cached_alpha);
+
+    assert_eq!(decompile_expression(&json), "lerp(blend_alpha(), blend_offset(), 30.5)");
+    assert!(diff(&parse_json(&json), &compile(&decompile_source(&json)), 0.0).is_empty());
+}
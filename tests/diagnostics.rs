@@ -0,0 +1,57 @@
+// Diagnostics show the source line with the span underlined
+use std::path::PathBuf;
+
+use worldgen_lang::diagnostic::Diagnostic;
+use worldgen_lang::format;
+use worldgen_lang::parser::{LangParser, SourceFiles};
+use worldgen_lang::parser::lexer::LangLexer;
+use worldgen_lang::util;
+
+fn render_format_error(source: &str) -> String {
+    let mut files = SourceFiles::new();
+    let file = files.add(PathBuf::from("test.json"), String::from(source));
+
+    let element = LangParser::new_with_file(LangLexer::new(source), file).parse_full()
+        .unwrap_or_else(|err| panic!("Failed to parse {}: {}", source, err));
+    let error = format::format_json(element, format::Options::default()).expect_err("element has unresolved names");
+
+    Diagnostic::from(&error).render(&files, false)
+}
+
+#[test]
+fn test_unresolved_function_snippet() {
+    assert_eq!(render_format_error("{\n  \"a\": foo(1)\n}"), "\
+error[E0201]: Unresolved function 'foo'
+ --> test.json:2:8
+  |
+2 |   \"a\": foo(1)
+  |        ^^^^^^
+");
+}
+
+#[test]
+fn test_underline_keeps_tabs() {
+    let rendered = render_format_error("{\n\t\"a\": [1, bar]\n}");
+    assert!(rendered.ends_with("2 | \t\"a\": [1, bar]\n  | \t         ^^^\n  = note: names have to be defined with `let` or be functions without arguments\n"), "{}", rendered);
+}
+
+#[test]
+fn test_parse_error_snippet() {
+    let source = "[1, 2 3]";
+    let mut files = SourceFiles::new();
+    let file = files.add(PathBuf::from("test.json"), String::from(source));
+    let error = LangParser::new_with_file(LangLexer::new(source), file).parse_full().expect_err("missing comma");
+
+    let rendered = Diagnostic::from_parse_error(&error, file).render(&files, false);
+    assert!(rendered.ends_with("1 | [1, 2 3]\n  |       ^\n"), "{}", rendered);
+}
+
+#[test]
+fn test_closest_name() {
+    let names = ["cache_2d", "cache_once", "abs", "interpolated"];
+
+    assert_eq!(util::closest_name("cach_2d", names), Some("cache_2d"));
+    assert_eq!(util::closest_name("interpolate", names), Some("interpolated"));
+    assert_eq!(util::closest_name("abs", names), None);
+    assert_eq!(util::closest_name("noise", names), None);
+}
@@ -0,0 +1,38 @@
+// Differences between JSON values are found regardless of key order and number representation
+use worldgen_lang::diff::diff;
+use worldgen_lang::element::Element;
+use worldgen_lang::parser::LangParser;
+use worldgen_lang::parser::lexer::LangLexer;
+
+fn parse(source: &str) -> Element {
+    LangParser::new(LangLexer::new(source)).parse_full().unwrap_or_else(|err| panic!("Failed to parse {}: {}", source, err))
+}
+
+fn differences(left: &str, right: &str, tolerance: f64) -> Vec<String> {
+    diff(&parse(left), &parse(right), tolerance).iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_key_order_and_numbers_are_ignored() {
+    assert!(differences("{ \"a\": 1, \"b\": [2.0, \"c\"] }", "{ \"b\": [2, \"c\"], \"a\": 1.0 }", 0.0).is_empty());
+}
+
+#[test]
+fn test_differences_have_paths() {
+    let left = "{ \"type\": \"minecraft:noise\", \"argument2\": { \"argument1\": { \"xz_scale\": 0.25 } }, \"points\": [1, 2] }";
+    let right = "{ \"type\": \"minecraft:noise\", \"argument2\": { \"argument1\": { \"xz_scale\": 0.5 } }, \"points\": [1, 3, 4], \"extra\": null }";
+
+    assert_eq!(differences(left, right, 0.0), vec![
+        "argument2.argument1.xz_scale: 0.25 != 0.5",
+        "points[1]: 2 != 3",
+        "points[2]: (missing) != 4",
+        "extra: (missing) != null",
+    ]);
+}
+
+#[test]
+fn test_tolerance() {
+    assert_eq!(differences("0.3", "0.30000000000000004", 0.0), vec!["(root): 0.3 != 0.30000000000000004"]);
+    assert!(differences("0.3", "0.30000000000000004", 1e-9).is_empty());
+    assert!(!differences("0.3", "0.31", 1e-9).is_empty());
+}
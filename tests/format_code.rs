@@ -0,0 +1,59 @@
+// Formatting source files, keeping comments and blank lines
+use worldgen_lang::format::code::format_code;
+use worldgen_lang::parser::LangParser;
+use worldgen_lang::parser::lexer::LangLexer;
+
+fn format(source: &str) -> String {
+    let formatted = format_code(source).unwrap_or_else(|err| panic!("Failed to format {}: {}", source, err));
+    assert_eq!(format_code(&formatted).expect("formatted code parses"), formatted, "formatting is not idempotent");
+
+    formatted
+}
+
+#[test]
+fn test_idempotence() {
+    let source = "import  \"shared.json\" ;\n// Constants\nlet  a=1+2 ;let b = [ 1,2 ,3]; /* list */\n\n\nfn double(x) = x*2;\n{ \"x\": double( a ), \"y\": -b }\n";
+    let formatted = format(source);
+
+    assert_eq!(formatted, "import \"shared.json\";\n// Constants\nlet a = 1 + 2;\nlet b = [1, 2, 3]; /* list */\n\nfn double(x) = x * 2;\n{\n  \"x\": double(a),\n  \"y\": -b\n}\n");
+
+    // Formatting only changes the layout, not the declarations and the element
+    let (module, errors) = LangParser::new(LangLexer::new(source)).parse_module_with_recovery();
+    let (formatted_module, formatted_errors) = LangParser::new(LangLexer::new(&formatted)).parse_module_with_recovery();
+
+    assert!(errors.is_empty() && formatted_errors.is_empty(), "{:?} {:?}", errors, formatted_errors);
+    assert_eq!(module.declarations.len(), 4);
+    assert_eq!(formatted_module, module);
+}
+
+#[test]
+fn test_line_comments() {
+    assert_eq!(format("// First\nlet a = 1; // After a\n// Before b\nlet b = 2;\na + b\n"),
+        "// First\nlet a = 1; // After a\n// Before b\nlet b = 2;\na + b\n");
+}
+
+#[test]
+fn test_block_comments() {
+    // Block comments on their own line stay there, other block comments stay next to their code
+    assert_eq!(format("let a = 1;\n/* block */\nlet b = 2;\n/* inline */ let c = 3;\na + b /* sum */ + c\n"),
+        "let a = 1;\n/* block */\nlet b = 2;\n/* inline */ let c = 3;\na + b /* sum */ + c\n");
+    assert_eq!(format("/* first */\nlet a = 1;\na\n"), "/* first */\nlet a = 1;\na\n");
+}
+
+#[test]
+fn test_blank_lines() {
+    // Single blank lines are kept, more blank lines are merged into one
+    assert_eq!(format("let a = 1;\n\nlet b = 2;\n\n\n\nlet c = 3;\nlet d = 4;\na\n"),
+        "let a = 1;\n\nlet b = 2;\n\nlet c = 3;\nlet d = 4;\na\n");
+}
+
+#[test]
+fn test_wrapping() {
+    let short = "let a = add(noise(\"minecraft:ridge\", 0.25, 1.0), 1);\na\n";
+    assert_eq!(format(short), short);
+
+    let long = "let long = add(add(noise(\"minecraft:ridge\", 0.25, 1.0), noise(\"minecraft:jagged\", 1500.0, 0.0)), mul(\"minecraft:y\", 0.5));\nlong\n";
+    let formatted = format(long);
+    assert_eq!(formatted, "let long = add(\n  add(noise(\"minecraft:ridge\", 0.25, 1.0), noise(\"minecraft:jagged\", 1500.0, 0.0)),\n  mul(\"minecraft:y\", 0.5)\n);\nlong\n");
+    assert!(formatted.lines().all(|line| line.len() <= 100));
+}
@@ -0,0 +1,172 @@
+// Every output format has to produce JSON that parses back to the same element
+use std::fs;
+use std::path::Path;
+
+use worldgen_lang::element::Element;
+use worldgen_lang::format::{self, FloatPrecision, Layout, Options};
+use worldgen_lang::array_element;
+use worldgen_lang::parser::{LangParser, Span};
+use worldgen_lang::parser::lexer::LangLexer;
+
+const LAYOUTS: [Layout; 4] = [
+    Layout::Pretty { indentation: 2 },
+    Layout::Pretty { indentation: 4 },
+    Layout::Tabs,
+    Layout::Compressed,
+];
+
+const FLOAT_PRECISIONS: [FloatPrecision; 3] = [
+    FloatPrecision::Shortest,
+    FloatPrecision::Float32,
+    FloatPrecision::Fixed(3),
+];
+
+fn all_options() -> Vec<Options> {
+    let mut options = Vec::new();
+
+    for layout in LAYOUTS {
+        for float_precision in FLOAT_PRECISIONS {
+            for keep_decimal_point in [true, false] {
+                options.push(Options { layout, float_precision, keep_decimal_point });
+            }
+        }
+    }
+
+    options
+}
+
+fn parse(source: &str) -> Element {
+    LangParser::new(LangLexer::new(source)).parse_full()
+        .unwrap_or_else(|err| panic!("Failed to parse {}: {}", source, err))
+}
+
+fn format(element: &Element, options: Options) -> String {
+    format::format_json(element.clone(), options)
+        .unwrap_or_else(|err| panic!("Failed to format {:?}: {}", element, err))
+}
+
+// Integral floats can be written without a decimal point, and depending on the
+// precision, floats are rounded, so numbers are compared by value
+fn assert_same(expected: &Element, actual: &Element, precision: FloatPrecision) {
+    match (expected, actual) {
+        (Element::ArrayElement(expected_values, _), Element::ArrayElement(actual_values, _)) => {
+            assert_eq!(expected_values.len(), actual_values.len(), "{:?} != {:?}", expected, actual);
+
+            for (expected, actual) in expected_values.iter().zip(actual_values) {
+                assert_same(expected, actual, precision);
+            }
+        },
+        (Element::ObjectElement(expected_fields, _), Element::ObjectElement(actual_fields, _)) => {
+            assert_eq!(expected_fields.len(), actual_fields.len(), "{:?} != {:?}", expected, actual);
+
+            for ((expected_key, expected_value), (actual_key, actual_value)) in expected_fields.iter().zip(actual_fields) {
+                assert_same(expected_key, actual_key, precision);
+                assert_same(expected_value, actual_value, precision);
+            }
+        },
+        _ => match (expected.as_number(), actual.as_number()) {
+            (Some(expected), Some(actual)) => match precision {
+                FloatPrecision::Shortest => assert_eq!(expected, actual),
+                FloatPrecision::Float32 => assert_eq!(expected as f32, actual as f32),
+                FloatPrecision::Fixed(decimals) => assert!((expected - actual).abs() < 10f64.powi(-(decimals as i32)),
+                    "{} != {}", expected, actual),
+            },
+            _ => assert_eq!(expected, actual),
+        },
+    }
+}
+
+fn assert_round_trip(element: &Element) {
+    for options in all_options() {
+        let formatted = format(element, options);
+        assert_same(element, &parse(&formatted), options.float_precision);
+    }
+}
+
+fn visit_outputs(directory: &Path, files: &mut Vec<String>) {
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+
+        if path.is_dir() {
+            visit_outputs(&path, files);
+        } else {
+            files.push(fs::read_to_string(path).unwrap());
+        }
+    }
+}
+
+#[test]
+fn test_outputs_round_trip() {
+    let mut files = Vec::new();
+    visit_outputs(Path::new("test/vanilla/output"), &mut files);
+    visit_outputs(Path::new("test/custom/output"), &mut files);
+    assert!(!files.is_empty());
+
+    for file in files {
+        assert_round_trip(&parse(&file));
+    }
+}
+
+#[test]
+fn test_edge_cases_round_trip() {
+    assert_round_trip(&parse(r#"[]"#));
+    assert_round_trip(&parse(r#"{}"#));
+    assert_round_trip(&parse(r#"[[], {}, [[]], { "a": {} }]"#));
+    assert_round_trip(&parse(r#"{ "a": null, "b": true, "c": false, "d": -1, "e": 0.19999999, "f": -0.075 }"#));
+    assert_round_trip(&array_element!(
+        Element::StringElement(String::from("quote \" backslash \\ slash /"), Span::none()),
+        Element::StringElement(String::from("line\nbreak\ttab\rreturn \u{1} \u{7f}"), Span::none()),
+        Element::StringElement(String::from("unicode é ü 水 😀"), Span::none()),
+        Element::FloatElement(1e300, Span::none()),
+        Element::FloatElement(-1e-300, Span::none()),
+        Element::IntElement(i32::MIN, Span::none())
+    ));
+}
+
+#[test]
+fn test_compressed_has_no_whitespace() {
+    let element = parse(r#"{ "a": [1, 2, { "b": "c d" }], "e": {} }"#);
+    assert_eq!(format(&element, Options::new(Layout::Compressed)), r#"{"a":[1,2,{"b":"c d"}],"e":{}}"#);
+}
+
+#[test]
+fn test_tabs_indentation() {
+    let element = parse(r#"{ "a": [1] }"#);
+    assert_eq!(format(&element, Options::new(Layout::Tabs)), "{\n\t\"a\": [\n\t\t1\n\t]\n}");
+}
+
+#[test]
+fn test_non_finite_numbers_fail() {
+    for value in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        for options in all_options() {
+            assert!(format::format_json(Element::FloatElement(value, Span::none()), options).is_err());
+        }
+    }
+}
+
+fn format_float(value: f64, float_precision: FloatPrecision, keep_decimal_point: bool) -> String {
+    format(&Element::FloatElement(value, Span::none()), Options { layout: Layout::Compressed, float_precision, keep_decimal_point })
+}
+
+#[test]
+fn test_float_formatting() {
+    assert_eq!(format_float(-0.07500000000000001, FloatPrecision::Shortest, true), "-0.07500000000000001");
+    assert_eq!(format_float(-0.07500000000000001, FloatPrecision::Float32, true), "-0.075");
+    assert_eq!(format_float(0.19999999, FloatPrecision::Float32, true), "0.19999999");
+    assert_eq!(format_float(0.123456, FloatPrecision::Fixed(3), true), "0.123");
+    assert_eq!(format_float(0.5, FloatPrecision::Fixed(3), true), "0.5");
+
+    assert_eq!(format_float(1.0, FloatPrecision::Shortest, true), "1.0");
+    assert_eq!(format_float(1.0, FloatPrecision::Shortest, false), "1");
+    assert_eq!(format_float(2.0, FloatPrecision::Fixed(3), true), "2.0");
+    assert_eq!(format_float(-0.0, FloatPrecision::Shortest, true), "-0.0");
+
+    // Same notation as Java's Double.toString
+    assert_eq!(format_float(2.9802322E-8, FloatPrecision::Shortest, true), "2.9802322E-8");
+    assert_eq!(format_float(0.001, FloatPrecision::Shortest, true), "0.001");
+    assert_eq!(format_float(1e7, FloatPrecision::Shortest, true), "1.0E7");
+    assert_eq!(format_float(1e7, FloatPrecision::Shortest, false), "1E7");
+    assert_eq!(format_float(1e40, FloatPrecision::Float32, true), "1.0E40");
+
+    assert_eq!(format(&Element::IntElement(1, Span::none()), Options::new(Layout::Compressed)), "1");
+}
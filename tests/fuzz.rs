@@ -0,0 +1,126 @@
+// Random inputs must never make the lexer, parser, formatter or processor panic
+use worldgen_lang::builtins::create_processor;
+use worldgen_lang::format;
+use worldgen_lang::format::code::format_code;
+use worldgen_lang::parser::{LangParser, ParseError, MAX_DEPTH, MAX_OPERATORS};
+use worldgen_lang::parser::lexer::{LangLexer, LangTokenType};
+use worldgen_lang::processor::ElementProcessor;
+
+// Pieces of valid and invalid source code, random inputs are made by joining them
+const FRAGMENTS: &[&str] = &[
+    "{", "}", "[", "]", "(", ")", ",", ":", ";", "'", ".", "=", "<", "<=", ">", ">=", "+", "-", "->", "*", "/",
+    "//", "/*", "*/", "\"", "\\", "\"a\"", "\"a\\\"b\"", "\"\\u00e9\"", "\"\\ud800\"", "\"\\u12\"", "\"\\q\"",
+    "0", "1", "-1", "0.5", "1.", "1.e", "1e", "1e-3", "1E+", "2147483647", "2147483648", "-2147483648", "1e400",
+    "x", "abs", "min", "noise", "y_gradient", "lerp", "clamp", "spline", "let", "fn", "import", "if", "then", "else",
+    "true", "false", "null", "é", "水", "😀", "\u{0}", "#", "@", " ", "\n", "\t", "\r\n",
+];
+
+// xorshift64, so that failures can be reproduced from the seed
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+}
+
+fn random_source(random: &mut Random) -> String {
+    let length = random.below(40);
+    let mut source = String::new();
+
+    for _ in 0..length {
+        if random.below(10) == 0 { // Single random characters
+            source.push(char::from_u32(random.below(0x3000) as u32).unwrap_or('?'));
+        } else {
+            source.push_str(FRAGMENTS[random.below(FRAGMENTS.len())]);
+        }
+    }
+
+    source
+}
+
+fn check(source: &str, processor: &ElementProcessor<'_>) {
+    for keep_comments in [false, true] {
+        let mut lexer = if keep_comments { LangLexer::new_with_comments(source) } else { LangLexer::new(source) };
+
+        // Every token or error moves forward, so the lexer always reaches the end
+        for _ in 0..=source.len() {
+            if lexer.scan_token().is_ok_and(|token| token.token_type() == LangTokenType::Eof) {
+                break;
+            }
+        }
+    }
+
+    let _ = LangParser::new(LangLexer::new(source)).parse_full();
+    let _ = format_code(source);
+
+    let (module, _) = LangParser::new(LangLexer::new(source)).parse_module_with_recovery();
+    let result = processor.process_module(module);
+
+    if let Some(element) = result.element {
+        let _ = format::format_json(element, format::Options::default());
+    }
+}
+
+#[test]
+fn test_random_inputs_dont_panic() {
+    let processor = create_processor();
+
+    for seed in 1..=3000u64 {
+        let mut random = Random(seed.wrapping_mul(0x9E3779B97F4A7C15));
+        let source = random_source(&mut random);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| check(&source, &processor)));
+        assert!(result.is_ok(), "Panicked on input {:?} (seed {})", source, seed);
+    }
+}
+
+#[test]
+fn test_edge_cases_dont_panic() {
+    let processor = create_processor();
+
+    for source in ["", "-", "1", "1.", "1e", "-1e", "\"", "\"\\", "\"a\\\"b\"", "/", "/*", "/* /* */", "é", "\"é\" é",
+        "1 + 2147483647", "-2147483648 * -1", "2147483647 * 2", "1 / 0", "1e308 * 10", "x.", "f(", "spline(", "if"] {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| check(source, &processor)));
+        assert!(result.is_ok(), "Panicked on input {:?}", source);
+    }
+}
+
+#[test]
+fn test_deep_nesting_is_an_error() {
+    let source = "[".repeat(100000) + &"]".repeat(100000);
+    assert!(matches!(LangParser::new(LangLexer::new(&source)).parse_full(), Err(ParseError::TooDeeplyNested(_))));
+
+    assert!(format_code(&source).is_err());
+
+    let source = "1".to_owned() + &" + 1".repeat(100000);
+    assert!(matches!(LangParser::new(LangLexer::new(&source)).parse_full(), Err(ParseError::TooManyOperators(_))));
+}
+
+#[test]
+fn test_long_sums_are_not_nesting() {
+    let source = "\"minecraft:x\"".to_owned() + &" + \"minecraft:y\"".repeat(MAX_OPERATORS);
+    assert!(LangParser::new(LangLexer::new(&source)).parse_full().is_ok());
+
+    let error = LangParser::new(LangLexer::new(&(source + " + 1"))).parse_full().expect_err("too many operators");
+    assert_eq!(error.message(), format!("Expressions can have at most {} operators", MAX_OPERATORS));
+}
+
+#[test]
+fn test_deepest_allowed_nesting_is_processed() {
+    let processor = create_processor();
+
+    // Every call counts two levels, one for the function and one for the call
+    let calls = MAX_DEPTH / 2 - 1;
+    let source = "abs(".repeat(calls) + &"x + ".repeat(MAX_OPERATORS) + "1" + &")".repeat(calls);
+
+    assert!(LangParser::new(LangLexer::new(&source)).parse_full().is_ok());
+    check(&source, &processor);
+}
@@ -0,0 +1,104 @@
+// Compiles every file in test/*/input and compares the results with test/*/output, ignoring
+// the order of keys and float noise.
+// Run with BLESS=1 to write the current results as the new expected output.
+use std::path::{Path, PathBuf};
+
+use worldgen_lang::{builtins, format, io};
+use worldgen_lang::compile::compile_file;
+use worldgen_lang::diff::{diff, Difference};
+use worldgen_lang::element::Element;
+use worldgen_lang::import::Importer;
+use worldgen_lang::io::FileResult;
+use worldgen_lang::parser::LangParser;
+use worldgen_lang::parser::lexer::LangLexer;
+
+const SUITES: &[&str] = &["vanilla", "custom"];
+
+// Floats that differ less than this (relative to their size) are the same
+const FLOAT_TOLERANCE: f64 = 1e-9;
+
+fn files(directory: &Path) -> Vec<PathBuf> {
+    io::list_files(directory).map(|files| files.into_iter().map(|file| directory.join(file)).collect()).unwrap_or_default()
+}
+
+fn parse_json(source: &str) -> Result<Element, String> {
+    LangParser::new(LangLexer::new(source)).parse_full().map_err(|err| err.to_string())
+}
+
+// Checks one suite, returning its failures. When blessing, expected output that differs is
+// overwritten and expected output without an input file is removed.
+fn check_suite(suite: &Path, bless: bool) -> Vec<String> {
+    let input = suite.join("input");
+    let output = suite.join("output");
+
+    let importer = Importer::new(input.clone());
+    let processor = builtins::create_processor();
+    let mut failures = Vec::new();
+    let mut expected_files = Vec::new();
+
+    for path in files(&input) {
+        let relative_path = path.strip_prefix(&input).expect("file is in the input directory");
+        let expected_path = output.join(relative_path);
+        let name = relative_path.to_string_lossy();
+
+        let source = std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("Could not read {}: {}", path.display(), err));
+        let compilation = compile_file(&path, source, &importer, &processor, format::Options::default());
+
+        let actual = match compilation.result {
+            FileResult::Output(actual) => actual,
+            FileResult::NoOutput => continue,
+            FileResult::Failed => {
+                let diagnostics: String = compilation.diagnostics.iter().map(|diagnostic| diagnostic.render(&compilation.files, false)).collect();
+                failures.push(format!("{} failed to compile:\n{}", name, diagnostics));
+                continue;
+            },
+        };
+
+        expected_files.push(expected_path.clone());
+
+        let differences = match std::fs::read_to_string(&expected_path) {
+            Ok(expected) => match (parse_json(&expected), parse_json(&actual)) {
+                (Ok(expected), Ok(actual)) => diff(&expected, &actual, FLOAT_TOLERANCE).iter().map(Difference::to_string).collect(),
+                (Err(err), _) => vec![format!("expected output is not valid JSON: {}", err)],
+                (_, Err(err)) => vec![format!("output is not valid JSON: {}", err)],
+            },
+            Err(_) => vec![String::from("no expected output")],
+        };
+
+        if differences.is_empty() {
+            continue;
+        }
+
+        if bless {
+            std::fs::create_dir_all(expected_path.parent().expect("output file has a directory")).expect("create output directory");
+            std::fs::write(&expected_path, actual).unwrap_or_else(|err| panic!("Could not write {}: {}", expected_path.display(), err));
+            println!("Blessed {}", expected_path.display());
+        } else {
+            failures.push(format!("{} differs from {}:\n  {}", name, expected_path.display(), differences.join("\n  ")));
+        }
+    }
+
+    for path in files(&output).into_iter().filter(|path| !expected_files.contains(path)) {
+        if bless {
+            std::fs::remove_file(&path).unwrap_or_else(|err| panic!("Could not remove {}: {}", path.display(), err));
+            println!("Removed {}", path.display());
+        } else {
+            failures.push(format!("{} has no input file", path.display()));
+        }
+    }
+
+    failures
+}
+
+#[test]
+fn test_golden_files() {
+    let bless = std::env::var_os("BLESS").is_some_and(|value| value == "1");
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("test");
+
+    let failures: Vec<String> = SUITES.iter()
+        .flat_map(|suite| check_suite(&root.join(suite), bless))
+        .collect();
+
+    assert!(failures.is_empty(), "{} golden file failures (run with BLESS=1 to accept the new output):\n\n{}",
+        failures.len(), failures.join("\n\n"));
+}
@@ -0,0 +1,41 @@
+// Edge cases of the lexer, seen through the errors and elements of the parser
+use worldgen_lang::element::Element;
+use worldgen_lang::parser::{LangParser, ParseError};
+use worldgen_lang::parser::lexer::LangLexer;
+
+#[test]
+fn test_tokens_after_top_level_element() {
+    let error = LangParser::new(LangLexer::new("[1, 2] 3")).parse_full().expect_err("trailing token");
+    assert!(matches!(error, ParseError::UnexpectedToken(pos, _) if pos.column == 7));
+
+    let (module, errors) = LangParser::new(LangLexer::new("let a = 1;\na }")).parse_module_with_recovery();
+    assert_eq!(errors.iter().map(|error| error.pos().map(|pos| pos.line)).collect::<Vec<_>>(), vec![Some(1)]);
+    assert!(module.element.is_some());
+}
+
+#[test]
+fn test_strings_with_escaped_quotes_and_non_ascii() {
+    let element = LangParser::new(LangLexer::new("{ \"a\\\"b\": \"é水😀\" }")).parse_full().expect("valid object");
+    assert_eq!(element.field("a\"b"), Some(&Element::StringElement(String::from("é水😀"), Default::default())));
+
+    let error = LangParser::new(LangLexer::new("[1, é]")).parse_full().expect_err("invalid character");
+    assert!(matches!(error, ParseError::UnexpectedCharacter(pos, 'é') if pos.column == 4));
+}
+
+#[test]
+fn test_unterminated_string_and_comment() {
+    let error = LangParser::new(LangLexer::new("[1, \"a\\\"]")).parse_full().expect_err("unterminated string");
+    assert!(matches!(error, ParseError::UnterminatedString(pos) if pos.column == 4));
+
+    let error = LangParser::new(LangLexer::new("1 /* a /* b */")).parse_full().expect_err("unterminated comment");
+    assert!(matches!(error, ParseError::UnterminatedComment(pos) if pos.column == 2));
+}
+
+#[test]
+fn test_numbers_at_end_of_input() {
+    assert_eq!(LangParser::new(LangLexer::new("-1")).parse_full().ok(), Some(Element::IntElement(-1, Default::default())));
+    assert_eq!(LangParser::new(LangLexer::new("2.5e3")).parse_full().ok(), Some(Element::FloatElement(2500.0, Default::default())));
+    assert!(LangParser::new(LangLexer::new("-")).parse_full().is_err());
+    assert!(LangParser::new(LangLexer::new("1.")).parse_full().is_err());
+    assert!(LangParser::new(LangLexer::new("1e")).parse_full().is_err());
+}
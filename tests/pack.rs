@@ -0,0 +1,159 @@
+// Compiling into a data pack zip
+use std::path::{Path, PathBuf};
+
+use worldgen_lang::io::{self, FileResult};
+use worldgen_lang::pack::{self, PackOptions};
+use worldgen_lang::zip::{crc32, ZipWriter};
+
+fn u16_at(data: &[u8], offset: usize) -> usize {
+    u16::from_le_bytes([data[offset], data[offset + 1]]) as usize
+}
+
+fn u32_at(data: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]]) as usize
+}
+
+// Reads the entries of a zip without compression through its central directory, checking
+// their checksums
+fn read_zip(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let end = data.len() - 22;
+    assert_eq!(u32_at(data, end), 0x06054b50, "end of central directory");
+
+    let mut offset = u32_at(data, end + 16);
+    let mut entries = Vec::new();
+
+    for _ in 0..u16_at(data, end + 10) {
+        assert_eq!(u32_at(data, offset), 0x02014b50, "central directory header");
+        let crc = u32_at(data, offset + 16) as u32;
+        let size = u32_at(data, offset + 24);
+        let name_length = u16_at(data, offset + 28);
+        let name = String::from_utf8(data[offset + 46..offset + 46 + name_length].to_vec()).expect("name is UTF-8");
+
+        let local = u32_at(data, offset + 42);
+        assert_eq!(u32_at(data, local), 0x04034b50, "local header");
+        let start = local + 30 + u16_at(data, local + 26) + u16_at(data, local + 28);
+        let contents = data[start..start + size].to_vec();
+        assert_eq!(crc32(&contents), crc, "checksum of {}", name);
+
+        entries.push((name, contents));
+        offset += 46 + name_length + u16_at(data, offset + 30) + u16_at(data, offset + 32);
+    }
+
+    entries
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF43926);
+}
+
+#[test]
+fn test_zip_writer() {
+    let mut zip = ZipWriter::new();
+    zip.add("a.txt", b"Hello").expect("add a.txt");
+    zip.add("folder/b.bin", &[0, 255, 13, 10]).expect("add b.bin");
+    zip.add("empty", b"").expect("add empty");
+
+    assert_eq!(zip.len(), 3);
+    assert!(zip.contains("folder/b.bin"));
+
+    let entries = read_zip(&zip.finish().expect("finish zip"));
+    assert_eq!(entries, vec![
+        (String::from("a.txt"), b"Hello".to_vec()),
+        (String::from("folder/b.bin"), vec![0, 255, 13, 10]),
+        (String::from("empty"), Vec::new()),
+    ]);
+}
+
+#[test]
+fn test_pack_paths() {
+    let options = PackOptions { namespace: String::from("test"), ..PackOptions::new() };
+
+    assert_eq!(pack::pack_path(Path::new("overworld/offset.json"), &options), "data/test/worldgen/density_function/overworld/offset.json");
+    assert_eq!(pack::pack_path(Path::new("data/test/worldgen/noise_settings/a.json"), &options), "data/test/worldgen/noise_settings/a.json");
+    assert_eq!(pack::pack_path(Path::new("pack.png"), &options), "pack.png");
+    assert_eq!(pack::pack_path(Path::new("notes/readme.txt"), &options), "notes/readme.txt");
+}
+
+#[test]
+fn test_pack_mcmeta() {
+    let options = PackOptions { pack_format: 15, description: String::from("A \"quoted\" pack"), ..PackOptions::new() };
+    assert_eq!(pack::pack_mcmeta(&options), "{\n  \"pack\": {\n    \"pack_format\": 15,\n    \"description\": \"A \\\"quoted\\\" pack\"\n  }\n}");
+}
+
+fn compile(_: &Path, input_str: String) -> (FileResult, String) {
+    match input_str.as_str() {
+        "fail" => (FileResult::Failed, String::from("- Failed\n")),
+        "definitions" => (FileResult::NoOutput, String::new()),
+        _ => (FileResult::Output(input_str.to_uppercase()), String::new()),
+    }
+}
+
+#[test]
+fn test_process_to_zip() {
+    let directory = std::env::temp_dir().join(format!("worldgen_lang_pack_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    let files: &[(&str, &[u8])] = &[
+        ("overworld/offset.json", b"offset"),
+        ("shared.json", b"definitions"),
+        ("data/test/worldgen/noise_settings/a.json", b"settings"),
+        ("pack.png", &[137, 80, 78, 71, 0, 255]),
+    ];
+
+    for (file, contents) in files {
+        let path = directory.join("input").join(file);
+        std::fs::create_dir_all(path.parent().expect("file has a directory")).expect("create input directory");
+        std::fs::write(path, contents).expect("write input file");
+    }
+
+    let options = PackOptions { namespace: String::from("test"), description: String::from("Test"), ..PackOptions::new() };
+    pack::process_to_zip(directory.join("input"), directory.join("pack.zip"), 2, &options, &compile);
+
+    let mut entries = read_zip(&std::fs::read(directory.join("pack.zip")).expect("zip was written"));
+    entries.sort();
+
+    assert_eq!(entries, vec![
+        (String::from("data/test/worldgen/density_function/overworld/offset.json"), b"OFFSET".to_vec()),
+        (String::from("data/test/worldgen/noise_settings/a.json"), b"SETTINGS".to_vec()),
+        (String::from("pack.mcmeta"), pack::pack_mcmeta(&options).into_bytes()),
+        (String::from("pack.png"), vec![137, 80, 78, 71, 0, 255]),
+    ]);
+
+    // A pack.mcmeta in the input replaces the generated one, and nothing is written when a file fails
+    std::fs::write(directory.join("input/pack.mcmeta"), "custom").expect("write pack.mcmeta");
+    pack::process_to_zip(directory.join("input"), directory.join("custom.zip"), 1, &options, &compile);
+
+    let entries = read_zip(&std::fs::read(directory.join("custom.zip")).expect("zip was written"));
+    assert!(entries.contains(&(String::from("pack.mcmeta"), b"custom".to_vec())));
+    assert_eq!(entries.iter().filter(|(name, _)| name == "pack.mcmeta").count(), 1);
+
+    std::fs::write(directory.join("input/broken.json"), "fail").expect("write broken file");
+    pack::process_to_zip(directory.join("input"), directory.join("failed.zip"), 1, &options, &compile);
+    assert!(!directory.join("failed.zip").exists());
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_duplicate_entries() {
+    let directory = std::env::temp_dir().join(format!("worldgen_lang_duplicates_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    // Both end up at data/minecraft/worldgen/density_function/foo.json
+    for file in ["foo.json", "data/minecraft/worldgen/density_function/foo.json"] {
+        let path = directory.join("input").join(file);
+        std::fs::create_dir_all(path.parent().expect("file has a directory")).expect("create input directory");
+        std::fs::write(path, "foo").expect("write input file");
+    }
+
+    pack::process_to_zip(directory.join("input"), directory.join("pack.zip"), 2, &PackOptions::new(), &compile);
+    assert!(!directory.join("pack.zip").exists());
+
+    let error = io::Error::DuplicateEntry(String::from("data/minecraft/worldgen/density_function/foo.json"),
+        PathBuf::from("data/minecraft/worldgen/density_function/foo.json"), PathBuf::from("foo.json"));
+    assert_eq!(error.to_string(), "Both data/minecraft/worldgen/density_function/foo.json and foo.json would be written to data/minecraft/worldgen/density_function/foo.json");
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
@@ -0,0 +1,110 @@
+// Directories can be processed on multiple threads, with the same results as on one thread
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use worldgen_lang::builtins;
+use worldgen_lang::io::{self, FileResult};
+
+fn is_send_and_sync<T: Send + Sync>(_: &T) -> bool {
+    true
+}
+
+#[test]
+fn test_processor_can_be_shared_between_threads() {
+    assert!(is_send_and_sync(&builtins::create_processor()));
+}
+
+#[test]
+fn test_process_parallel() {
+    let directory = std::env::temp_dir().join(format!("worldgen_lang_parallel_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+
+    for i in 0..20 {
+        let path = directory.join("input").join(format!("{}/{}.json", i % 3, i));
+        std::fs::create_dir_all(path.parent().expect("file has a directory")).expect("create input directory");
+        std::fs::write(path, i.to_string()).expect("write input file");
+    }
+
+    io::process_parallel(directory.join("input"), directory.join("output"), 4, &|_, input_str| {
+        match input_str.parse::<i32>() {
+            Ok(value) if value % 7 == 0 => (FileResult::Failed, String::from("- Multiple of 7\n")),
+            Ok(value) if value % 5 == 0 => (FileResult::NoOutput, String::new()),
+            Ok(value) => (FileResult::Output((value * 2).to_string()), String::new()),
+            Err(_) => (FileResult::Failed, String::new()),
+        }
+    });
+
+    for i in 0..20 {
+        let output = std::fs::read_to_string(directory.join("output").join(format!("{}/{}.json", i % 3, i)));
+
+        if i % 7 == 0 || i % 5 == 0 {
+            assert!(output.is_err(), "{} has no output", i);
+        } else {
+            assert_eq!(output.ok(), Some((i * 2).to_string()));
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_messages_in_file_order() {
+    let directory = std::env::temp_dir().join(format!("worldgen_lang_messages_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).expect("create input directory");
+
+    // The first file finishes last, but its messages still come first
+    let contents = ["slow", "fail", "definitions", "unwritable", "fast"];
+    let files: Vec<PathBuf> = (0..contents.len()).map(|i| PathBuf::from(format!("{}.json", i))).collect();
+
+    for (file, contents) in files.iter().zip(contents) {
+        std::fs::write(directory.join(file), contents).expect("write input file");
+    }
+
+    let mut written = Vec::new();
+    let mut out = Vec::new();
+
+    let failed = io::process_files(&directory, &files, 4, &|_: &Path, input_str: String| {
+        match input_str.as_str() {
+            "slow" => {
+                std::thread::sleep(Duration::from_millis(200));
+                (FileResult::Output(input_str), String::new())
+            },
+            "fail" => (FileResult::Failed, String::from("- Failed\n")),
+            "definitions" => (FileResult::NoOutput, String::new()),
+            _ => (FileResult::Output(input_str), String::new()),
+        }
+    }, |file, output_str| {
+        if output_str == "unwritable" {
+            return Err(io::Error::UnknownFile(file.to_path_buf()));
+        }
+
+        written.push(output_str);
+        Ok(())
+    }, &mut out);
+
+    assert_eq!(String::from_utf8(out).expect("messages are UTF-8"), "\
+Processing 0.json
+Processing 1.json
+- Failed
+
+Processing 2.json
+Processing 3.json
+Errors:
+- Unknown file (path is neither a file nor a directory): 3.json
+
+Processing 4.json
+");
+    assert_eq!(written, vec!["slow", "fast"]);
+    assert_eq!(failed, vec![&files[1], &files[3]]);
+
+    let mut summary = Vec::new();
+    io::write_summary(&mut summary, &failed, files.len());
+    assert_eq!(String::from_utf8(summary).expect("summary is UTF-8"), "Done with errors, 2 of 5 files failed:\n- 1.json\n- 3.json\n");
+
+    let mut summary = Vec::new();
+    io::write_summary(&mut summary, &[], files.len());
+    assert_eq!(String::from_utf8(summary).expect("summary is UTF-8"), "Done.\n");
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
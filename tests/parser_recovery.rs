@@ -0,0 +1,62 @@
+// The parser reports every syntax error in a file and keeps the parts around them
+use worldgen_lang::element::Element;
+use worldgen_lang::parser::{LangParser, ParseError};
+use worldgen_lang::parser::lexer::LangLexer;
+
+fn parse(source: &str) -> (Element, Vec<ParseError>) {
+    LangParser::new(LangLexer::new(source)).parse_full_with_recovery()
+}
+
+fn error_lines(errors: &[ParseError]) -> Vec<i32> {
+    errors.iter().map(|error| error.pos().expect("error has a position").line).collect()
+}
+
+#[test]
+fn test_errors_in_every_field() {
+    let (element, errors) = parse("{\n  \"a\": [1, 2 3],\n  \"b\": (1 + ),\n  \"c\": { \"x\" 1 },\n  \"d\": 4\n}");
+
+    assert_eq!(error_lines(&errors), vec![1, 2, 3]);
+    assert_eq!(element.field("d"), Some(&Element::IntElement(4, Default::default())));
+    assert!(matches!(element.field("b"), Some(Element::ErrorElement(_))));
+}
+
+#[test]
+fn test_missing_comma_keeps_both_items() {
+    let (element, errors) = parse("[1, 2 3]");
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&element, Element::ArrayElement(values, _) if values.len() == 3));
+}
+
+#[test]
+fn test_unclosed_bracket() {
+    let (element, errors) = parse("{ \"a\": [1, 2 }");
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0], ParseError::ExpectedToken { .. }));
+    assert!(element.field("a").is_some());
+}
+
+#[test]
+fn test_declarations_recover_at_semicolon() {
+    let (module, errors) = LangParser::new(LangLexer::new("let a = 1 +;\nlet b = (;\nfn f(x) = x;\nf(1)"))
+        .parse_module_with_recovery();
+
+    assert_eq!(error_lines(&errors), vec![0, 1]);
+    assert_eq!(module.declarations.len(), 2); // `b` is declared with an error element
+    assert!(module.element.is_some_and(|element| !element.contains_error()));
+}
+
+#[test]
+fn test_first_error_without_recovery() {
+    let error = LangParser::new(LangLexer::new("[1, , 2 3]")).parse_full().expect_err("invalid array");
+    assert_eq!(error.pos().map(|pos| pos.column), Some(4));
+}
+
+#[test]
+fn test_valid_source_has_no_errors() {
+    let (element, errors) = parse("{ \"a\": [1, 2, 3], \"b\": spline(\"c\") { 0: 1, 1: 2 ' 0.5 }, \"c\": f(x: 1), }");
+
+    assert!(errors.is_empty(), "{:?}", errors);
+    assert!(!element.contains_error());
+}
@@ -0,0 +1,44 @@
+// Functions that are added from a registry file
+use worldgen_lang::element::Element;
+use worldgen_lang::parser::LangParser;
+use worldgen_lang::parser::lexer::LangLexer;
+use worldgen_lang::processor::ElementProcessor;
+use worldgen_lang::processor::registry::{self, RegistryError};
+
+fn load(registry: &str) -> Result<ElementProcessor<'static>, Vec<RegistryError>> {
+    let registry: Element = LangParser::new(LangLexer::new(registry)).parse_full().unwrap_or_else(|err| panic!("Failed to parse {}: {}", registry, err));
+    let mut processor = ElementProcessor::new();
+
+    registry::load_registry(&mut processor, &registry).map(|_| processor)
+}
+
+fn errors(registry: &str) -> Vec<String> {
+    match load(registry) {
+        Ok(_) => panic!("{} loaded", registry),
+        Err(errors) => errors.iter().map(|error| format!("{}: {}", error.code(), error)).collect(),
+    }
+}
+
+fn function(arguments: &str) -> String {
+    format!(r#"{{ "functions": [ {{ "name": "test", "arguments": [ {} ] }} ] }}"#, arguments)
+}
+
+#[test]
+fn test_argument_types() {
+    let processor = load(&function(r#"{ "key": "noise", "type": "noise_id" }, { "key": "scale", "type": "number", "default": 1.0 }"#))
+        .unwrap_or_else(|errors| panic!("{:?}", errors.iter().map(ToString::to_string).collect::<Vec<_>>()));
+    assert!(processor.functions().iter().any(|function| function.name() == "test"));
+
+    assert_eq!(errors(&function(r#"{ "key": "noise", "type": "noise" }"#)), vec![
+        r#"E0303: Field "type" of function 'test' must be one of "density_function", "number", "int", "string", "noise_id" or "any", got: "noise""#,
+    ]);
+}
+
+#[test]
+fn test_defaults_match_their_type() {
+    assert_eq!(errors(&function(r#"{ "key": "scale", "type": "number", "default": "large" }"#)), vec![
+        r#"E0305: Default of argument 'scale' of function 'test' must be a number, got: "large""#,
+    ]);
+    assert_eq!(errors(&function(r#"{ "key": "count", "type": "int", "default": 1.5 }"#)).len(), 1);
+    assert_eq!(errors(&function(r#"{ "key": "noise", "type": "noise_id", "default": "Not An Id" }"#)).len(), 1);
+}
@@ -0,0 +1,48 @@
+// Polling finds added, changed and removed files, and dependents of changed files are found
+use std::path::PathBuf;
+
+use worldgen_lang::watch::{Dependencies, FileWatcher};
+
+fn temp_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("worldgen_lang_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(directory.join("sub")).expect("create temporary directory");
+    directory.canonicalize().expect("temporary directory exists")
+}
+
+#[test]
+fn test_poll_finds_changes() {
+    let directory = temp_directory("poll");
+    std::fs::write(directory.join("a.json"), "1").expect("write a");
+    std::fs::write(directory.join("sub/b.json"), "2").expect("write b");
+
+    let mut watcher = FileWatcher::new(vec![directory.clone()]);
+    assert!(watcher.poll().is_empty());
+
+    std::fs::write(directory.join("a.json"), "[1, 2]").expect("change a"); // Different size, in case the time doesn't change
+    std::fs::remove_file(directory.join("sub/b.json")).expect("remove b");
+    std::fs::write(directory.join("sub/c.json"), "3").expect("write c");
+
+    let changes = watcher.poll();
+    assert_eq!(changes.changed, vec![directory.join("a.json")]);
+    assert_eq!(changes.removed, vec![directory.join("sub/b.json")]);
+    assert_eq!(changes.added, vec![directory.join("sub/c.json")]);
+    assert!(watcher.poll().is_empty());
+
+    let _ = std::fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_dependents() {
+    let mut dependencies = Dependencies::new();
+    dependencies.set(PathBuf::from("/in/a.json"), vec![PathBuf::from("/in/a.json"), PathBuf::from("/in/defs.json")]);
+    dependencies.set(PathBuf::from("/in/b.json"), vec![PathBuf::from("/in/b.json"), PathBuf::from("/prelude/p.json")]);
+    dependencies.set(PathBuf::from("/in/defs.json"), vec![PathBuf::from("/in/defs.json")]);
+
+    assert_eq!(dependencies.dependents(&[PathBuf::from("/in/defs.json")]),
+        vec![PathBuf::from("/in/a.json"), PathBuf::from("/in/defs.json")]);
+    assert_eq!(dependencies.dependents(&[PathBuf::from("/prelude/p.json")]), vec![PathBuf::from("/in/b.json")]);
+
+    dependencies.remove(&PathBuf::from("/in/b.json"));
+    assert!(dependencies.dependents(&[PathBuf::from("/prelude/p.json")]).is_empty());
+}